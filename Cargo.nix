@@ -842,6 +842,7 @@ in {
     dependencies = {
       cortex_m = rustPackages."registry+https://github.com/rust-lang/crates.io-index".cortex-m."0.7.5" {inherit profileName;};
       cortex_m_rt = rustPackages."registry+https://github.com/rust-lang/crates.io-index".cortex-m-rt."0.7.1" {inherit profileName;};
+      critical_section = rustPackages."registry+https://github.com/rust-lang/crates.io-index".critical-section."0.2.7" {inherit profileName;};
       defmt = rustPackages."registry+https://github.com/rust-lang/crates.io-index".defmt."0.3.2" {inherit profileName;};
       defmt_rtt = rustPackages."registry+https://github.com/rust-lang/crates.io-index".defmt-rtt."0.3.2" {inherit profileName;};
       embedded_hal = rustPackages."registry+https://github.com/rust-lang/crates.io-index".embedded-hal."0.2.7" {inherit profileName;};
@@ -1067,6 +1068,13 @@ in {
     version = "0.1.0";
     registry = "unknown";
     src = fetchCrateLocal (workspaceSrc + "/lib/tinyptr");
+    features = builtins.concatLists [
+      ["default"]
+      ["exposed-provenance"]
+    ];
+    dependencies = {
+      critical_section = rustPackages."registry+https://github.com/rust-lang/crates.io-index".critical-section."0.2.7" {inherit profileName;};
+    };
   });
 
   "unknown".tinyptr-alloc."0.1.0" = overridableMkRustCrate (profileName: rec {
@@ -7,11 +7,378 @@
 //! Cargo re-run the build script whenever `memory.x` is changed,
 //! updating `memory.x` ensures a rebuild of the application with the
 //! new memory settings.
+//!
+//! It also turns the board configuration in `firmware.toml` into the
+//! `config` module, and run-length encodes the images in `assets` for the
+//! `assets` module.
 
+use std::collections::HashMap;
 use std::env;
-use std::fs::File;
+use std::fmt::Write as _;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Type of a configuration value
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    U8,
+    U32,
+    Usize,
+    Bool,
+}
+
+impl Kind {
+    fn rust_type(self) -> &'static str {
+        match self {
+            Kind::U8 => "u8",
+            Kind::U32 => "u32",
+            Kind::Usize => "usize",
+            Kind::Bool => "bool",
+        }
+    }
+    fn max(self) -> u64 {
+        match self {
+            Kind::U8 => u8::MAX.into(),
+            Kind::U32 | Kind::Usize => u32::MAX.into(),
+            Kind::Bool => 1,
+        }
+    }
+}
+
+/// A value in the configuration file
+#[derive(Clone, Debug)]
+enum Value {
+    Integer(u64),
+    Bool(bool),
+}
+
+/// A configuration setting
+struct Setting {
+    section: &'static str,
+    key: &'static str,
+    kind: Kind,
+    default: &'static str,
+    doc: &'static str,
+}
+
+/// Documentation of the sections, in the order they are generated
+const SECTIONS: &[(&str, &str)] = &[
+    ("matrix", "Key matrix"),
+    ("timing", "Timing defaults"),
+    ("leds", "LED chain"),
+    ("features", "Optional features"),
+];
+
+/// All known settings
+const SETTINGS: &[Setting] = &[
+    Setting {
+        section: "matrix",
+        key: "rows",
+        kind: Kind::Usize,
+        default: "4",
+        doc: "Number of rows",
+    },
+    Setting {
+        section: "matrix",
+        key: "cols",
+        kind: Kind::Usize,
+        default: "12",
+        doc: "Number of columns",
+    },
+    Setting {
+        section: "timing",
+        key: "debounce_ms",
+        kind: Kind::U32,
+        default: "5",
+        doc: "Debounce time in milliseconds",
+    },
+    Setting {
+        section: "timing",
+        key: "tapping_term_ms",
+        kind: Kind::U32,
+        default: "200",
+        doc: "Time in milliseconds before a tap turns into a hold",
+    },
+    Setting {
+        section: "leds",
+        key: "count",
+        kind: Kind::Usize,
+        default: "0",
+        doc: "Number of LEDs on the chain",
+    },
+    Setting {
+        section: "leds",
+        key: "brightness",
+        kind: Kind::U8,
+        default: "255",
+        doc: "Initial brightness (0-255)",
+    },
+    Setting {
+        section: "features",
+        key: "rgb",
+        kind: Kind::Bool,
+        default: "false",
+        doc: "RGB LED chain",
+    },
+    Setting {
+        section: "features",
+        key: "encoders",
+        kind: Kind::Bool,
+        default: "false",
+        doc: "Rotary encoders",
+    },
+    Setting {
+        section: "features",
+        key: "display",
+        kind: Kind::Bool,
+        default: "false",
+        doc: "SPI display",
+    },
+];
+
+/// Parses a value, returning `None` if the syntax is not supported
+fn parse_value(value: &str) -> Option<Value> {
+    match value {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        _ => {}
+    }
+    let digits = value.replace('_', "");
+    let integer = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+    integer.ok().map(Value::Integer)
+}
+
+/// Removes a trailing comment from a line
+fn strip_comment(line: &str) -> &str {
+    line.split_once('#').map_or(line, |(line, _)| line)
+}
+
+/// Parses the configuration file into a map of `(section, key)` to value
+///
+/// Only the subset of TOML used by `firmware.toml` is supported.
+fn parse_config(path: &Path, source: &str) -> HashMap<(String, String), Value> {
+    let mut values = HashMap::new();
+    let mut section = String::new();
+    for (number, line) in source.lines().enumerate() {
+        let error = |message: &str| -> ! {
+            panic!("{}:{}: {}", path.display(), number + 1, message);
+        };
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .unwrap_or_else(|| error("invalid section header"));
+            if !SECTIONS.iter().any(|&(known, _)| known == name.trim()) {
+                error(&format!("unknown section `{}`", name.trim()));
+            }
+            section = name.trim().to_owned();
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .unwrap_or_else(|| error("expected `key = value`"));
+        let key = key.trim();
+        let setting = SETTINGS
+            .iter()
+            .find(|setting| setting.section == section && setting.key == key)
+            .unwrap_or_else(|| error(&format!("unknown setting `{}` in [{}]", key, section)));
+        let value = parse_value(value.trim()).unwrap_or_else(|| error("unsupported value"));
+        match (&value, setting.kind) {
+            (Value::Bool(_), Kind::Bool) => {}
+            (Value::Integer(n), kind) if kind != Kind::Bool => {
+                if *n > kind.max() {
+                    error(&format!("`{}` does not fit in {}", key, kind.rust_type()));
+                }
+            }
+            _ => error(&format!(
+                "`{}` must be of type {}",
+                key,
+                setting.kind.rust_type()
+            )),
+        }
+        if values
+            .insert((section.clone(), key.to_owned()), value)
+            .is_some()
+        {
+            error(&format!("duplicate setting `{}`", key));
+        }
+    }
+    values
+}
+
+/// Generates the `config` module from the configuration file
+fn generate_config(out: &Path) {
+    println!("cargo:rerun-if-env-changed=RKBFIRM_CONFIG");
+    let path = env::var_os("RKBFIRM_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("firmware.toml"));
+    println!("cargo:rerun-if-changed={}", path.display());
+    let source = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+    let values = parse_config(&path, &source);
+
+    let mut config = String::new();
+    for &(section, doc) in SECTIONS {
+        writeln!(config, "/// {}\npub mod {} {{", doc, section).unwrap();
+        for setting in SETTINGS.iter().filter(|setting| setting.section == section) {
+            let value = match values.get(&(section.to_owned(), setting.key.to_owned())) {
+                Some(Value::Integer(n)) => n.to_string(),
+                Some(Value::Bool(b)) => b.to_string(),
+                None => setting.default.to_owned(),
+            };
+            writeln!(
+                config,
+                "    /// {}\n    pub const {}: {} = {};",
+                setting.doc,
+                setting.key.to_uppercase(),
+                setting.kind.rust_type(),
+                value
+            )
+            .unwrap();
+        }
+        writeln!(config, "}}").unwrap();
+    }
+    fs::write(out.join("config.rs"), config).unwrap();
+}
+
+/// Directory with the images that are embedded into the firmware
+const ASSET_DIR: &str = "assets";
+
+/// Returns the next whitespace separated token of a PPM header, skipping comments
+fn next_token<'a>(data: &'a [u8], pos: &mut usize) -> &'a str {
+    loop {
+        while data.get(*pos).map_or(false, u8::is_ascii_whitespace) {
+            *pos += 1;
+        }
+        if data.get(*pos) != Some(&b'#') {
+            break;
+        }
+        while data.get(*pos).map_or(false, |&b| b != b'\n') {
+            *pos += 1;
+        }
+    }
+    let start = *pos;
+    while data.get(*pos).map_or(false, |b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&data[start..*pos]).unwrap_or("")
+}
+
+/// Reads a binary (`P6`) or plain (`P3`) PPM image and converts it to RGB565
+///
+/// Only a maximum sample value of 255 is supported.
+fn read_ppm(path: &Path) -> (u16, u16, Vec<u16>) {
+    let data = fs::read(path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+    let error = |message: &str| -> ! {
+        panic!("{}: {}", path.display(), message);
+    };
+    let number = |pos: &mut usize| -> usize {
+        next_token(&data, pos)
+            .parse()
+            .unwrap_or_else(|_| error("expected a number"))
+    };
+    let mut pos = 0;
+    let magic = next_token(&data, &mut pos);
+    let width = number(&mut pos);
+    let height = number(&mut pos);
+    if number(&mut pos) != 255 {
+        error("only a maximum value of 255 is supported");
+    }
+    let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) if width != 0 && height != 0 => (width, height),
+        _ => error("invalid image size"),
+    };
+    let samples = usize::from(width) * usize::from(height) * 3;
+    let samples: Vec<u8> = match magic {
+        // A single whitespace character separates the header from the samples
+        "P6" => data
+            .get(pos + 1..pos + 1 + samples)
+            .unwrap_or_else(|| error("truncated image"))
+            .to_vec(),
+        "P3" => (0..samples)
+            .map(|_| {
+                u8::try_from(number(&mut pos)).unwrap_or_else(|_| error("sample out of range"))
+            })
+            .collect(),
+        _ => error("not a PPM image"),
+    };
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|rgb| {
+            let [r, g, b] = [rgb[0], rgb[1], rgb[2]].map(u16::from);
+            ((r & 0xF8) << 8) | ((g & 0xFC) << 3) | (b >> 3)
+        })
+        .collect();
+    (width, height, pixels)
+}
+
+/// Encodes an image in the run-length format read by `st7789::Image`
+fn encode_rle(width: u16, height: u16, pixels: &[u16]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    let mut pixels = pixels.iter().peekable();
+    while let Some(&color) = pixels.next() {
+        let mut count = 1u8;
+        while count < u8::MAX && pixels.next_if_eq(&&color).is_some() {
+            count += 1;
+        }
+        data.push(count);
+        data.extend_from_slice(&color.to_le_bytes());
+    }
+    data
+}
+
+/// Encodes the images in `assets` and generates the `assets` module
+fn generate_assets(out: &Path) {
+    println!("cargo:rerun-if-changed={}", ASSET_DIR);
+    let mut paths: Vec<PathBuf> = match fs::read_dir(ASSET_DIR) {
+        Ok(dir) => dir
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "ppm"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+
+    let mut assets = String::new();
+    for path in paths {
+        let name = path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            panic!("{}: file name is not a valid identifier", path.display());
+        }
+        let (width, height, pixels) = read_ppm(&path);
+        fs::write(
+            out.join(format!("{}.rle", name)),
+            encode_rle(width, height, &pixels),
+        )
+        .unwrap();
+        writeln!(
+            assets,
+            "/// `{}`, {}x{} pixels\npub static {}: Image = \
+             Image::new(include_bytes!(concat!(env!(\"OUT_DIR\"), \"/{}.rle\")));",
+            path.display(),
+            width,
+            height,
+            name.to_uppercase(),
+            name
+        )
+        .unwrap();
+    }
+    fs::write(out.join("assets.rs"), assets).unwrap();
+}
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -28,5 +395,7 @@ fn main() {
     // here, we ensure the build script is only re-run when
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
+    generate_config(out);
+    generate_assets(out);
     println!("cargo:rustc-env=GIT_VERSION={}", git_version::git_version!());
 }
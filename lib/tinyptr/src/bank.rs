@@ -0,0 +1,251 @@
+//! Pools with a runtime base address
+//!
+//! The const generic base of [`ConstPtr`](crate::ptr::ConstPtr) has to be known at compile
+//! time. A [`Bank`] is registered at startup instead, e.g. for a pool in a linker-placed static,
+//! and [`BankedPtr`]s store the offset into the bank plus the bank id.
+//!
+//! Offset 0 is the start of a bank, so null banked pointers use the otherwise unused bank id
+//! [`NULL_BANK`] instead.
+
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicU16, Ordering},
+};
+
+use crate::Pointable;
+
+/// Maximum number of banks that can be registered
+pub const MAX_BANKS: usize = 4;
+/// Bank id of null banked pointers
+pub const NULL_BANK: u8 = u8::MAX;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const UNREGISTERED: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+static BANKS: [AtomicPtr<u8>; MAX_BANKS] = [UNREGISTERED; MAX_BANKS];
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicU16 = AtomicU16::new(0);
+/// Size of each bank minus one, so the full 64 kiB fit
+static BANK_LIMITS: [AtomicU16; MAX_BANKS] = [EMPTY; MAX_BANKS];
+
+/// Error returned when registering a bank fails
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BankError {
+    /// The base pointer is null
+    Null,
+    /// The memory is empty or larger than the 64 kiB address space
+    InvalidSize,
+    /// All [`MAX_BANKS`] slots are in use
+    Full,
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BankError::Null => f.write_str("bank base is null"),
+            BankError::InvalidSize => f.write_str("bank must be between 1 byte and 64 kiB"),
+            BankError::Full => f.write_str("too many banks registered"),
+        }
+    }
+}
+
+/// Error returned when a pointer cannot be converted to a [`BankedPtr`]
+#[derive(Debug, Clone)]
+pub enum BankedPtrError<T: ?Sized + Pointable> {
+    /// The value is not completely inside the bank
+    OutOfBank,
+    /// The pointer metadata cannot be reduced in size
+    CannotReduceMeta(<T as Pointable>::ConversionError),
+}
+
+/// Handle of a registered bank
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bank(u8);
+
+impl Bank {
+    /// Registers `len` bytes of memory starting at `base` as a bank
+    ///
+    /// # Errors
+    /// This function returns an error if `base` is null, the memory does not fit into the
+    /// address space or all banks are in use.
+    pub fn register(base: *mut u8, len: usize) -> Result<Self, BankError> {
+        // A null base would mark the slot as free again
+        if base.is_null() {
+            return Err(BankError::Null);
+        }
+        if len == 0 || len > 0x1_0000 {
+            return Err(BankError::InvalidSize);
+        }
+        critical_section::with(|_| {
+            let id = BANKS
+                .iter()
+                .position(|bank| bank.load(Ordering::Relaxed).is_null())
+                .ok_or(BankError::Full)?;
+            BANK_LIMITS[id].store((len - 1) as u16, Ordering::Relaxed);
+            BANKS[id].store(base, Ordering::Release);
+            Ok(Self(id as u8))
+        })
+    }
+    /// Returns the id of the bank
+    pub const fn id(self) -> u8 {
+        self.0
+    }
+    /// Returns the base pointer of the bank
+    pub fn base(self) -> *mut u8 {
+        BANKS[usize::from(self.0)].load(Ordering::Acquire)
+    }
+    /// Returns the size of the bank in bytes
+    pub fn size(self) -> usize {
+        usize::from(BANK_LIMITS[usize::from(self.0)].load(Ordering::Relaxed)) + 1
+    }
+    /// Converts a pointer into the bank to a banked pointer
+    ///
+    /// A null pointer is converted to a null banked pointer.
+    ///
+    /// # Errors
+    /// This function returns an error if the value is not completely inside the bank or the
+    /// metadata of the pointer does not fit into the tiny version.
+    pub fn ptr<T: Pointable + ?Sized>(
+        self,
+        ptr: *mut T,
+    ) -> Result<BankedPtr<T>, BankedPtrError<T>> {
+        let (addr, meta) = T::extract_parts(ptr);
+        let size = T::size_of_val(&meta);
+        let meta = T::try_tiny(meta).map_err(BankedPtrError::CannotReduceMeta)?;
+        if ptr.is_null() {
+            return Ok(BankedPtr::from_raw_parts(0, NULL_BANK, meta));
+        }
+        let offset = addr.wrapping_sub(self.base().addr());
+        match size.and_then(|size| offset.checked_add(size)) {
+            Some(end) if offset < self.size() && end <= self.size() => {
+                Ok(BankedPtr::from_raw_parts(offset as u16, self.0, meta))
+            }
+            _ => Err(BankedPtrError::OutOfBank),
+        }
+    }
+}
+
+/// A tiny pointer into a [`Bank`]
+pub struct BankedPtr<T: Pointable + ?Sized> {
+    offset: u16,
+    bank: u8,
+    meta: <T as Pointable>::PointerMetaTiny,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T: Pointable + ?Sized> BankedPtr<T> {
+    const fn from_raw_parts(offset: u16, bank: u8, meta: T::PointerMetaTiny) -> Self {
+        Self {
+            offset,
+            bank,
+            meta,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns a null banked pointer
+    pub const fn null() -> Self
+    where
+        T: Pointable<PointerMetaTiny = ()>,
+    {
+        Self::from_raw_parts(0, NULL_BANK, ())
+    }
+    /// Returns `true` if the pointer is null
+    pub const fn is_null(self) -> bool {
+        self.bank == NULL_BANK
+    }
+    /// Returns the bank the pointer points into, or `None` if the pointer is null
+    pub const fn bank(self) -> Option<Bank> {
+        if self.is_null() {
+            None
+        } else {
+            Some(Bank(self.bank))
+        }
+    }
+    /// Returns the offset of the pointer into its bank
+    pub const fn offset(self) -> u16 {
+        self.offset
+    }
+    /// Widens the pointer
+    pub fn wide(self) -> *mut T {
+        let meta = T::huge(self.meta);
+        match self.bank() {
+            Some(bank) => {
+                let base = bank.base();
+                let addr = base.addr() + usize::from(self.offset);
+                T::create_ptr_mut(base.cast(), addr, meta)
+            }
+            None => T::create_ptr_mut(ptr::null_mut(), 0, meta),
+        }
+    }
+    /// Returns a reference to the value, or `None` if the pointer is null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to point to a valid value of `T` that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_ref<'a>(self) -> Option<&'a T> {
+        self.wide().as_ref()
+    }
+    /// Returns a mutable reference to the value, or `None` if the pointer is null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to point to a valid value of `T` that is not accessed
+    /// through any other pointer for `'a`.
+    pub unsafe fn as_mut<'a>(self) -> Option<&'a mut T> {
+        self.wide().as_mut()
+    }
+    /// Reads the value from self without moving it. this leaves the memory in self unchanged.
+    ///
+    /// # Safety
+    /// The pointer has to be valid for reads and point to an initialized value.
+    pub unsafe fn read(self) -> T
+    where
+        T: Sized,
+    {
+        self.wide().read()
+    }
+    /// Overwrites a memory location with the given value without reading or dropping the old value
+    ///
+    /// # Safety
+    /// The pointer has to be valid for writes.
+    pub unsafe fn write(self, val: T)
+    where
+        T: Sized,
+    {
+        self.wide().write(val)
+    }
+    /// Casts to a pointer of another type
+    pub const fn cast<U: Pointable<PointerMetaTiny = ()>>(self) -> BankedPtr<U> {
+        BankedPtr::from_raw_parts(self.offset, self.bank, ())
+    }
+}
+
+impl<T: Pointable + ?Sized> Clone for BankedPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T: Pointable + ?Sized> Copy for BankedPtr<T> {}
+impl<T: Pointable + ?Sized> PartialEq for BankedPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset && self.bank == other.bank && self.meta == other.meta
+    }
+}
+impl<T: Pointable + ?Sized> Eq for BankedPtr<T> {}
+impl<T: Pointable + ?Sized> Hash for BankedPtr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.offset.hash(state);
+        self.bank.hash(state);
+        self.meta.hash(state);
+    }
+}
+impl<T: Pointable + ?Sized> fmt::Debug for BankedPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_null() {
+            f.write_str("null")
+        } else {
+            write!(f, "{}:{:#06x}", self.bank, self.offset)
+        }
+    }
+}
@@ -0,0 +1,6 @@
+//! Shareable containers living inside a pool
+
+mod once_cell;
+pub use once_cell::*;
+mod tiny_cell;
+pub use tiny_cell::*;
@@ -0,0 +1,195 @@
+//! One-time initialization
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    ops::Deref,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::Ref;
+
+/// A cell that can be written to only once
+///
+/// The cell is meant to be placed inside a pool (usually as a `static`) and hands out tiny
+/// references to its contents. Initialization happens inside a critical section, so it is safe
+/// to race between interrupt handlers and the main loop.
+pub struct TinyOnceCell<T, const BASE: usize> {
+    initialized: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: The value is only written once, inside a critical section, before `initialized` is set
+unsafe impl<T: Send + Sync, const BASE: usize> Sync for TinyOnceCell<T, BASE> {}
+
+impl<T, const BASE: usize> TinyOnceCell<T, BASE> {
+    /// Creates a new empty cell
+    pub const fn new() -> Self {
+        Self {
+            initialized: AtomicBool::new(false),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+    /// Returns a reference to the value, if the cell is initialized
+    fn get_wide(&self) -> Option<&T> {
+        if self.initialized.load(Ordering::Acquire) {
+            // SAFETY: The value is initialized and never written to again
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+    /// Converts a reference to the value into a tiny reference
+    ///
+    /// # Panics
+    /// This function panics if the cell is not inside the address space
+    fn tiny(value: &T) -> Ref<'_, T, BASE> {
+        Ref::new(value).expect("TinyOnceCell is not inside the address space")
+    }
+    /// Returns a reference to the value, if the cell is initialized
+    ///
+    /// # Panics
+    /// This function panics if the cell is not inside the address space
+    pub fn get(&self) -> Option<Ref<'_, T, BASE>> {
+        self.get_wide().map(Self::tiny)
+    }
+    /// Returns a mutable reference to the value, if the cell is initialized
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.initialized.get_mut() {
+            // SAFETY: The value is initialized and we have exclusive access
+            Some(unsafe { self.value.get_mut().assume_init_mut() })
+        } else {
+            None
+        }
+    }
+    /// Initializes the cell with `value`
+    ///
+    /// # Errors
+    /// Returns the value if the cell was already initialized
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut value = Some(value);
+        self.get_or_init_wide(|| value.take().unwrap());
+        match value {
+            Some(value) => Err(value),
+            None => Ok(()),
+        }
+    }
+    /// Initializes the cell with `f` if it is uninitialized, returning the value
+    ///
+    /// `f` runs inside a critical section, so it should be short.
+    ///
+    /// # Panics
+    /// This function panics if `f` initializes the cell itself, or if the cell is not inside the
+    /// address space.
+    pub fn get_or_init(&self, f: impl FnOnce() -> T) -> Ref<'_, T, BASE> {
+        Self::tiny(self.get_or_init_wide(f))
+    }
+    fn get_or_init_wide(&self, f: impl FnOnce() -> T) -> &T {
+        if let Some(value) = self.get_wide() {
+            return value;
+        }
+        critical_section::with(|_| {
+            if self.initialized.load(Ordering::Acquire) {
+                return;
+            }
+            let value = f();
+            assert!(
+                !self.initialized.load(Ordering::Acquire),
+                "reentrant TinyOnceCell initialization"
+            );
+            // SAFETY: The cell is uninitialized, so there are no references to the value
+            unsafe {
+                (*self.value.get()).write(value);
+            }
+            self.initialized.store(true, Ordering::Release);
+        });
+        // SAFETY: The cell was initialized above
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+    /// Takes the value out of the cell, leaving it uninitialized
+    pub fn take(&mut self) -> Option<T> {
+        if core::mem::replace(self.initialized.get_mut(), false) {
+            // SAFETY: The value was initialized and the cell is marked as uninitialized now
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+    /// Consumes the cell, returning the value
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+}
+
+impl<T, const BASE: usize> Default for TinyOnceCell<T, BASE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const BASE: usize> Drop for TinyOnceCell<T, BASE> {
+    fn drop(&mut self) {
+        drop(self.take());
+    }
+}
+
+impl<T: fmt::Debug, const BASE: usize> fmt::Debug for TinyOnceCell<T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get_wide() {
+            Some(value) => f.debug_tuple("TinyOnceCell").field(value).finish(),
+            None => f.write_str("TinyOnceCell(<uninit>)"),
+        }
+    }
+}
+
+/// A value that is initialized on first access
+pub struct TinyLazy<T, const BASE: usize, F = fn() -> T> {
+    cell: TinyOnceCell<T, BASE>,
+    init: UnsafeCell<Option<F>>,
+}
+
+// SAFETY: `init` is only accessed inside the critical section of the cell initialization
+unsafe impl<T: Send + Sync, const BASE: usize, F: Send> Sync for TinyLazy<T, BASE, F> {}
+
+impl<T, const BASE: usize, F: FnOnce() -> T> TinyLazy<T, BASE, F> {
+    /// Creates a new lazy value with the given initializer
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: TinyOnceCell::new(),
+            init: UnsafeCell::new(Some(init)),
+        }
+    }
+    fn force_wide(this: &Self) -> &T {
+        this.cell.get_or_init_wide(|| {
+            // SAFETY: Only called once, inside the critical section
+            match unsafe { (*this.init.get()).take() } {
+                Some(init) => init(),
+                None => panic!("TinyLazy instance has previously been poisoned"),
+            }
+        })
+    }
+    /// Forces evaluation of the value and returns a reference to it
+    ///
+    /// # Panics
+    /// This function panics if the value is not inside the address space
+    pub fn force(this: &Self) -> Ref<'_, T, BASE> {
+        TinyOnceCell::<T, BASE>::tiny(Self::force_wide(this))
+    }
+}
+
+impl<T, const BASE: usize, F: FnOnce() -> T> Deref for TinyLazy<T, BASE, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        Self::force_wide(self)
+    }
+}
+
+impl<T: fmt::Debug, const BASE: usize, F> fmt::Debug for TinyLazy<T, BASE, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cell.get_wide() {
+            Some(value) => f.debug_tuple("TinyLazy").field(value).finish(),
+            None => f.write_str("TinyLazy(<uninit>)"),
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Shareable mutable containers
+
+use core::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{ptr::MutPtr, Ref};
+
+/// A mutable memory location inside a pool
+///
+/// Works like [`core::cell::Cell`], but hands out tiny pointers to its contents.
+#[repr(transparent)]
+pub struct TinyCell<T, const BASE: usize> {
+    value: UnsafeCell<T>,
+}
+
+impl<T, const BASE: usize> TinyCell<T, BASE> {
+    /// Creates a new cell
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+        }
+    }
+    /// Sets the value
+    pub fn set(&self, value: T) {
+        drop(self.replace(value));
+    }
+    /// Replaces the value, returning the old one
+    pub fn replace(&self, value: T) -> T {
+        // SAFETY: The cell is not Sync and never hands out references to its contents
+        unsafe { core::mem::replace(&mut *self.value.get(), value) }
+    }
+    /// Returns a copy of the value
+    pub fn get(&self) -> T
+    where
+        T: Copy,
+    {
+        // SAFETY: The cell is not Sync and never hands out references to its contents
+        unsafe { *self.value.get() }
+    }
+    /// Takes the value, leaving the default value in its place
+    pub fn take(&self) -> T
+    where
+        T: Default,
+    {
+        self.replace(T::default())
+    }
+    /// Returns a mutable reference to the value
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+    /// Returns a tiny pointer to the value
+    ///
+    /// # Panics
+    /// This function panics if the cell is not inside the address space
+    pub fn as_ptr(&self) -> MutPtr<T, BASE> {
+        MutPtr::new(self.value.get())
+            .unwrap_or_else(|_| panic!("TinyCell is not inside the address space"))
+    }
+    /// Consumes the cell, returning the value
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Default, const BASE: usize> Default for TinyCell<T, BASE> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Copy + fmt::Debug, const BASE: usize> fmt::Debug for TinyCell<T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TinyCell")
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+/// The value of a [`TinyRefCell`] is currently mutably borrowed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BorrowError;
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("already mutably borrowed")
+    }
+}
+
+/// The value of a [`TinyRefCell`] is currently borrowed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BorrowMutError;
+
+impl fmt::Display for BorrowMutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("already borrowed")
+    }
+}
+
+/// A mutable memory location inside a pool with dynamically checked borrow rules
+///
+/// Works like [`core::cell::RefCell`]. The borrow guards only hold a tiny reference to the
+/// cell, so they take 2 bytes.
+pub struct TinyRefCell<T, const BASE: usize> {
+    /// Number of shared borrows, or -1 while mutably borrowed
+    borrow: Cell<i16>,
+    value: UnsafeCell<T>,
+}
+
+impl<T, const BASE: usize> TinyRefCell<T, BASE> {
+    /// Creates a new cell
+    pub const fn new(value: T) -> Self {
+        Self {
+            borrow: Cell::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+    /// Converts a reference to the cell into a tiny reference
+    ///
+    /// # Panics
+    /// This function panics if the cell is not inside the address space
+    fn tiny(&self) -> Ref<'_, Self, BASE> {
+        Ref::new(self).expect("TinyRefCell is not inside the address space")
+    }
+    /// Immutably borrows the value
+    ///
+    /// # Errors
+    /// This function returns an error if the value is mutably borrowed.
+    ///
+    /// # Panics
+    /// This function panics if the cell is not inside the address space
+    pub fn try_borrow(&self) -> Result<TinyBorrow<'_, T, BASE>, BorrowError> {
+        let borrow = self.borrow.get();
+        if borrow < 0 {
+            return Err(BorrowError);
+        }
+        let borrow = borrow.checked_add(1).expect("too many TinyRefCell borrows");
+        // Converted before the flag is set, so a panic does not leave the cell borrowed
+        let cell = self.tiny();
+        self.borrow.set(borrow);
+        Ok(TinyBorrow { cell })
+    }
+    /// Immutably borrows the value
+    ///
+    /// # Panics
+    /// This function panics if the value is mutably borrowed or the cell is not inside the
+    /// address space.
+    pub fn borrow(&self) -> TinyBorrow<'_, T, BASE> {
+        self.try_borrow().expect("already mutably borrowed")
+    }
+    /// Mutably borrows the value
+    ///
+    /// # Errors
+    /// This function returns an error if the value is borrowed.
+    ///
+    /// # Panics
+    /// This function panics if the cell is not inside the address space
+    pub fn try_borrow_mut(&self) -> Result<TinyBorrowMut<'_, T, BASE>, BorrowMutError> {
+        if self.borrow.get() != 0 {
+            return Err(BorrowMutError);
+        }
+        // Converted before the flag is set, so a panic does not leave the cell borrowed
+        let cell = self.tiny();
+        self.borrow.set(-1);
+        Ok(TinyBorrowMut {
+            cell,
+            _marker: PhantomData,
+        })
+    }
+    /// Mutably borrows the value
+    ///
+    /// # Panics
+    /// This function panics if the value is borrowed or the cell is not inside the address
+    /// space.
+    pub fn borrow_mut(&self) -> TinyBorrowMut<'_, T, BASE> {
+        self.try_borrow_mut().expect("already borrowed")
+    }
+    /// Replaces the value, returning the old one
+    ///
+    /// # Panics
+    /// This function panics if the value is borrowed or the cell is not inside the address
+    /// space.
+    pub fn replace(&self, value: T) -> T {
+        core::mem::replace(&mut *self.borrow_mut(), value)
+    }
+    /// Returns a mutable reference to the value
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+    /// Consumes the cell, returning the value
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: Default, const BASE: usize> Default for TinyRefCell<T, BASE> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug, const BASE: usize> fmt::Debug for TinyRefCell<T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("TinyRefCell");
+        if self.borrow.get() < 0 {
+            d.field("value", &format_args!("<borrowed>"));
+        } else {
+            // SAFETY: The value is not mutably borrowed
+            d.field("value", unsafe { &*self.value.get() });
+        }
+        d.finish()
+    }
+}
+
+/// Shared borrow of the value of a [`TinyRefCell`]
+pub struct TinyBorrow<'b, T, const BASE: usize> {
+    cell: Ref<'b, TinyRefCell<T, BASE>, BASE>,
+}
+
+impl<T, const BASE: usize> Deref for TinyBorrow<'_, T, BASE> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: The borrow flag prevents mutable borrows while this guard exists
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T, const BASE: usize> Clone for TinyBorrow<'_, T, BASE> {
+    fn clone(&self) -> Self {
+        let borrow = self.cell.borrow.get();
+        self.cell
+            .borrow
+            .set(borrow.checked_add(1).expect("too many TinyRefCell borrows"));
+        Self { cell: self.cell }
+    }
+}
+
+impl<T, const BASE: usize> Drop for TinyBorrow<'_, T, BASE> {
+    fn drop(&mut self) {
+        self.cell.borrow.set(self.cell.borrow.get() - 1);
+    }
+}
+
+impl<T: fmt::Debug, const BASE: usize> fmt::Debug for TinyBorrow<'_, T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Mutable borrow of the value of a [`TinyRefCell`]
+pub struct TinyBorrowMut<'b, T, const BASE: usize> {
+    cell: Ref<'b, TinyRefCell<T, BASE>, BASE>,
+    _marker: PhantomData<&'b mut T>,
+}
+
+impl<T, const BASE: usize> Deref for TinyBorrowMut<'_, T, BASE> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: The borrow flag prevents other borrows while this guard exists
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T, const BASE: usize> DerefMut for TinyBorrowMut<'_, T, BASE> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The borrow flag prevents other borrows while this guard exists
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+impl<T, const BASE: usize> Drop for TinyBorrowMut<'_, T, BASE> {
+    fn drop(&mut self) {
+        self.cell.borrow.set(0);
+    }
+}
+
+impl<T: fmt::Debug, const BASE: usize> fmt::Debug for TinyBorrowMut<'_, T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -1,10 +1,12 @@
 //! 16 bit pointer library
 //!
 //! This library provides small pointers with a range of 64 kiB. This is useful for memory-limited
-//! microcontrollers.
+//! microcontrollers. The offset type of the pointers can be changed from `u16` to `u8` or `u32`
+//! for smaller or larger pools.
 //!
 //! It uses a const generic parameter to set the base address of the pointer. This allows multiple
 //! small memory pools to coexist.
+#![feature(arbitrary_self_types)]
 #![feature(coerce_unsized)]
 #![feature(const_trait_impl)]
 #![feature(mixed_integer_ops)]
@@ -18,9 +20,17 @@
 
 use core::hash::Hash;
 
+mod bank;
+pub use bank::*;
+pub mod cell;
+mod pool;
+pub use pool::*;
+pub(crate) use pool::{base_ptr, base_ptr_mut};
 pub mod ptr;
 mod tiny_ref;
 pub use tiny_ref::*;
+mod vtable;
+pub use vtable::*;
 
 /// Trait that defines valid destination types for a pointer.
 pub trait Pointable {
@@ -52,6 +62,9 @@ pub trait Pointable {
     }
     /// Convert a tiny version of the pointer metadata to the full version.
     fn huge(meta: Self::PointerMetaTiny) -> Self::PointerMeta;
+    /// Returns the size of the value a pointer with the metadata points to, or `None` if it
+    /// overflows a `usize`.
+    fn size_of_val(meta: &Self::PointerMeta) -> Option<usize>;
 
     /// Returns an address and the pointer metadata for a pointer
     fn extract_parts(ptr: *const Self) -> (usize, Self::PointerMeta);
@@ -73,6 +86,9 @@ impl<T: Sized> Pointable for T {
     }
     fn tiny(_: ()) -> () {}
     fn huge(_: ()) -> () {}
+    fn size_of_val(_: &()) -> Option<usize> {
+        Some(core::mem::size_of::<T>())
+    }
 
     fn extract_parts(ptr: *const Self) -> (usize, ()) {
         (ptr.addr(), ())
@@ -99,6 +115,9 @@ impl<T: Sized> Pointable for [T] {
     fn huge(meta: u16) -> usize {
         meta.into()
     }
+    fn size_of_val(meta: &usize) -> Option<usize> {
+        core::mem::size_of::<T>().checked_mul(*meta)
+    }
     fn extract_parts(ptr: *const Self) -> (usize, usize) {
         (ptr.as_ptr().addr(), ptr.len())
     }
@@ -110,16 +129,9 @@ impl<T: Sized> Pointable for [T] {
     }
 }
 
-pub(crate) fn base_ptr<const BASE: usize>() -> *const () {
-    core::ptr::from_exposed_addr(BASE)
-}
-pub(crate) fn base_ptr_mut<const BASE: usize>() -> *mut () {
-    core::ptr::from_exposed_addr_mut(BASE)
-}
-
 #[derive(Debug, Clone)]
 pub enum PointerConversionError<T: ?Sized + Pointable> {
-    /// The pointer is not in 16 bit address space
+    /// The pointer is not in the address space
     NotInAddressSpace(<u16 as TryFrom<usize>>::Error),
     /// The pointer metadata cannot be reduced in size
     CannotReduceMeta(<T as Pointable>::ConversionError),
@@ -0,0 +1,162 @@
+//! Pool registration
+//!
+//! Tiny pointers only store an offset, so widening them has to recreate the provenance of the
+//! full pointer. A pool that registers its memory once has all of its pointers derived from
+//! the registered pointer. Without registration, the address is converted back into a pointer
+//! with exposed provenance, if the `exposed-provenance` feature is enabled.
+//!
+//! Every base address has a home slot in the registry, computed at compile time. A pool is
+//! registered in its home slot unless another pool took it, so widening a pointer usually
+//! costs a single relaxed load.
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+use crate::ptr::Offset;
+
+/// Maximum number of pools that can be registered
+pub const MAX_POOLS: usize = 4;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const UNREGISTERED: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+static POOLS: [AtomicPtr<u8>; MAX_POOLS] = [UNREGISTERED; MAX_POOLS];
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicUsize = AtomicUsize::new(0);
+static POOL_SIZES: [AtomicUsize; MAX_POOLS] = [EMPTY; MAX_POOLS];
+
+/// Error returned when registering a pool fails
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegisterError {
+    /// The memory does not start at the base address of the pool
+    WrongAddress,
+    /// The memory is larger than the address space of the offset type
+    TooLarge,
+    /// A pool with the same base address is already registered
+    AlreadyRegistered,
+    /// All [`MAX_POOLS`] slots are in use
+    Full,
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::WrongAddress => f.write_str("memory does not start at the pool base"),
+            RegisterError::TooLarge => f.write_str("memory is larger than the address space"),
+            RegisterError::AlreadyRegistered => f.write_str("pool is already registered"),
+            RegisterError::Full => f.write_str("too many pools registered"),
+        }
+    }
+}
+
+/// Returns the registry slot that is tried first for a base address
+const fn home_slot(base: usize) -> usize {
+    // Pools are usually aligned to large powers of two, so the upper bits are folded in
+    let hash = base ^ (base >> 16);
+    let hash = hash ^ (hash >> 8);
+    (hash ^ (hash >> 4)) % MAX_POOLS
+}
+
+/// A memory pool at the base address `BASE`, addressed by offsets of type `O`
+///
+/// The offset type only limits the size of the pool; pools are looked up by their base address.
+pub struct Pool<const BASE: usize, O: Offset = u16>(PhantomData<O>);
+
+impl<const BASE: usize, O: Offset> Pool<BASE, O> {
+    /// Registry slot that is tried first
+    const HOME: usize = home_slot(BASE);
+
+    /// Registers the memory of the pool
+    ///
+    /// Pointers into the pool widened after this call derive their provenance from `base`.
+    ///
+    /// # Errors
+    /// This function returns an error if `base` is not at `BASE`, is larger than the address
+    /// space of `O`, or the pool cannot be registered.
+    pub fn register<const N: usize>(base: *mut [u8; N]) -> Result<(), RegisterError> {
+        if base.addr() != BASE {
+            return Err(RegisterError::WrongAddress);
+        }
+        if N.saturating_sub(1) > O::MAX {
+            return Err(RegisterError::TooLarge);
+        }
+        critical_section::with(|_| {
+            if Self::base_ptr().is_some() {
+                return Err(RegisterError::AlreadyRegistered);
+            }
+            // Pools are never unregistered, so lookups can stop at the first empty slot
+            let slot = Self::probe()
+                .find(|&slot| POOLS[slot].load(Ordering::Relaxed).is_null())
+                .ok_or(RegisterError::Full)?;
+            POOL_SIZES[slot].store(N, Ordering::Relaxed);
+            POOLS[slot].store(base.cast(), Ordering::Release);
+            Ok(())
+        })
+    }
+    /// Returns `true` if the pool is registered
+    pub fn is_registered() -> bool {
+        Self::base_ptr().is_some()
+    }
+    /// Returns the size of the pool in bytes, or `None` if it is not registered
+    pub fn size() -> Option<usize> {
+        let slot = Self::slot()?;
+        // Pairs with the release store of the pointer, which is stored after the size
+        POOLS[slot].load(Ordering::Acquire);
+        Some(POOL_SIZES[slot].load(Ordering::Relaxed))
+    }
+    /// Returns the registered pointer to the memory of the pool
+    ///
+    /// Only the pointer itself is read, so a relaxed load is enough.
+    #[inline]
+    pub(crate) fn base_ptr() -> Option<*mut u8> {
+        let base = POOLS[Self::HOME].load(Ordering::Relaxed);
+        if base.addr() == BASE && !base.is_null() {
+            return Some(base);
+        }
+        Some(POOLS[Self::slot()?].load(Ordering::Relaxed))
+    }
+    /// Returns the slots in the order they are tried, starting with the home slot
+    fn probe() -> impl Iterator<Item = usize> {
+        (0..MAX_POOLS).map(|i| (Self::HOME + i) % MAX_POOLS)
+    }
+    /// Returns the registry slot of the pool
+    fn slot() -> Option<usize> {
+        for slot in Self::probe() {
+            let base = POOLS[slot].load(Ordering::Relaxed);
+            if base.is_null() {
+                return None;
+            }
+            if base.addr() == BASE {
+                return Some(slot);
+            }
+        }
+        None
+    }
+}
+
+/// Returns a pointer to the base of the pool, carrying the provenance of the pool
+///
+/// # Panics
+/// This function panics if the pool is not registered and the `exposed-provenance` feature is
+/// disabled.
+pub(crate) fn base_ptr_mut<const BASE: usize>() -> *mut () {
+    match Pool::<BASE>::base_ptr() {
+        Some(base) => base.cast(),
+        #[cfg(feature = "exposed-provenance")]
+        None => ptr::from_exposed_addr_mut(BASE),
+        #[cfg(not(feature = "exposed-provenance"))]
+        None => panic!("pool is not registered"),
+    }
+}
+
+/// Returns a pointer to the base of the pool, carrying the provenance of the pool
+///
+/// # Panics
+/// This function panics if the pool is not registered and the `exposed-provenance` feature is
+/// disabled.
+pub(crate) fn base_ptr<const BASE: usize>() -> *const () {
+    base_ptr_mut::<BASE>()
+}
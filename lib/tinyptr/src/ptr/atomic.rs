@@ -0,0 +1,197 @@
+//! Atomic pointer
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    sync::atomic::{AtomicU16, Ordering},
+};
+
+use crate::Pointable;
+
+use super::MutPtr;
+
+/// A tiny pointer that can be shared between threads and interrupt handlers
+///
+/// Only thin pointers are supported, as the metadata would not fit into the atomic. On targets
+/// without atomic read-modify-write instructions (like the Cortex-M0+), stores and
+/// read-modify-write operations run in a critical section.
+#[repr(transparent)]
+pub struct AtomicTinyPtr<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> {
+    ptr: AtomicU16,
+    _marker: PhantomData<MutPtr<T, BASE>>,
+}
+
+unsafe impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> Send for AtomicTinyPtr<T, BASE> {}
+unsafe impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> Sync for AtomicTinyPtr<T, BASE> {}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> AtomicTinyPtr<T, BASE> {
+    /// Creates a new atomic pointer
+    pub const fn new(ptr: MutPtr<T, BASE>) -> Self {
+        Self {
+            ptr: AtomicU16::new(ptr.ptr),
+            _marker: PhantomData,
+        }
+    }
+    /// Consumes the atomic and returns the contained pointer
+    pub fn into_inner(self) -> MutPtr<T, BASE> {
+        MutPtr::from_raw_parts(self.ptr.into_inner(), ())
+    }
+    /// Loads the pointer
+    ///
+    /// # Panics
+    /// This function panics if `order` is `Release` or `AcqRel`.
+    pub fn load(&self, order: Ordering) -> MutPtr<T, BASE> {
+        MutPtr::from_raw_parts(self.ptr.load(order), ())
+    }
+    /// Stores a pointer
+    ///
+    /// # Panics
+    /// This function panics if `order` is `Acquire` or `AcqRel`.
+    pub fn store(&self, ptr: MutPtr<T, BASE>, order: Ordering) {
+        // A plain store could land between the load and store of a read-modify-write operation
+        // on the other core
+        #[cfg(not(target_has_atomic = "16"))]
+        critical_section::with(|_| self.ptr.store(ptr.ptr, order));
+        #[cfg(target_has_atomic = "16")]
+        self.ptr.store(ptr.ptr, order);
+    }
+    /// Stores a pointer, returning the previous one
+    pub fn swap(&self, ptr: MutPtr<T, BASE>, order: Ordering) -> MutPtr<T, BASE> {
+        #[cfg(target_has_atomic = "16")]
+        let previous = self.ptr.swap(ptr.ptr, order);
+        #[cfg(not(target_has_atomic = "16"))]
+        let previous = match self.update(order, Ordering::Relaxed, |_| Some(ptr.ptr)) {
+            Ok(previous) | Err(previous) => previous,
+        };
+        MutPtr::from_raw_parts(previous, ())
+    }
+    /// Stores `new` if the current pointer is `current`
+    ///
+    /// # Errors
+    /// This function returns the current pointer if it is not `current`.
+    ///
+    /// # Panics
+    /// This function panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange(
+        &self,
+        current: MutPtr<T, BASE>,
+        new: MutPtr<T, BASE>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<MutPtr<T, BASE>, MutPtr<T, BASE>> {
+        #[cfg(target_has_atomic = "16")]
+        let result = self
+            .ptr
+            .compare_exchange(current.ptr, new.ptr, success, failure);
+        #[cfg(not(target_has_atomic = "16"))]
+        let result = self.update(success, failure, |ptr| {
+            (ptr == current.ptr).then_some(new.ptr)
+        });
+        result
+            .map(|ptr| MutPtr::from_raw_parts(ptr, ()))
+            .map_err(|ptr| MutPtr::from_raw_parts(ptr, ()))
+    }
+    /// Stores `new` if the current pointer is `current`, possibly failing spuriously
+    ///
+    /// # Errors
+    /// This function returns the current pointer if it is not `current` or the exchange failed
+    /// spuriously.
+    ///
+    /// # Panics
+    /// This function panics if `failure` is `Release` or `AcqRel`.
+    pub fn compare_exchange_weak(
+        &self,
+        current: MutPtr<T, BASE>,
+        new: MutPtr<T, BASE>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<MutPtr<T, BASE>, MutPtr<T, BASE>> {
+        #[cfg(target_has_atomic = "16")]
+        let result = self
+            .ptr
+            .compare_exchange_weak(current.ptr, new.ptr, success, failure)
+            .map(|ptr| MutPtr::from_raw_parts(ptr, ()))
+            .map_err(|ptr| MutPtr::from_raw_parts(ptr, ()));
+        // The critical section never fails spuriously
+        #[cfg(not(target_has_atomic = "16"))]
+        let result = self.compare_exchange(current, new, success, failure);
+        result
+    }
+    /// Updates the pointer with `f` until the update succeeds, returning the previous pointer
+    ///
+    /// # Errors
+    /// This function returns the current pointer if `f` returns `None`.
+    ///
+    /// # Panics
+    /// This function panics if `fetch_order` is `Release` or `AcqRel`.
+    pub fn fetch_update(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: impl FnMut(MutPtr<T, BASE>) -> Option<MutPtr<T, BASE>>,
+    ) -> Result<MutPtr<T, BASE>, MutPtr<T, BASE>> {
+        let f = |ptr| f(MutPtr::from_raw_parts(ptr, ())).map(|ptr: MutPtr<T, BASE>| ptr.ptr);
+        #[cfg(target_has_atomic = "16")]
+        let result = self.ptr.fetch_update(set_order, fetch_order, f);
+        #[cfg(not(target_has_atomic = "16"))]
+        let result = self.update(set_order, fetch_order, f);
+        result
+            .map(|ptr| MutPtr::from_raw_parts(ptr, ()))
+            .map_err(|ptr| MutPtr::from_raw_parts(ptr, ()))
+    }
+    /// Replaces the pointer with the result of `f` in a critical section
+    ///
+    /// Returns the previous pointer, as `Err` if `f` returned `None`.
+    ///
+    /// # Panics
+    /// This function panics if `failure` is `Release` or `AcqRel`.
+    #[cfg(not(target_has_atomic = "16"))]
+    fn update(
+        &self,
+        success: Ordering,
+        failure: Ordering,
+        f: impl FnOnce(u16) -> Option<u16>,
+    ) -> Result<u16, u16> {
+        let (load, store) = match success {
+            Ordering::Release => (Ordering::Relaxed, Ordering::Release),
+            Ordering::Acquire => (Ordering::Acquire, Ordering::Relaxed),
+            Ordering::AcqRel => (Ordering::Acquire, Ordering::Release),
+            order => (order, order),
+        };
+        // The load has to satisfy the failure ordering as well
+        let load = match (load, failure) {
+            (_, Ordering::Release | Ordering::AcqRel) => {
+                panic!("there is no such thing as a release failure ordering")
+            }
+            (Ordering::SeqCst, _) | (_, Ordering::SeqCst) => Ordering::SeqCst,
+            (Ordering::Acquire, _) | (_, Ordering::Acquire) => Ordering::Acquire,
+            _ => Ordering::Relaxed,
+        };
+        critical_section::with(|_| {
+            let current = self.ptr.load(load);
+            let new = f(current).ok_or(current)?;
+            self.ptr.store(new, store);
+            Ok(current)
+        })
+    }
+}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> Default for AtomicTinyPtr<T, BASE> {
+    fn default() -> Self {
+        Self::new(MutPtr::from_raw_parts(0, ()))
+    }
+}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> From<MutPtr<T, BASE>>
+    for AtomicTinyPtr<T, BASE>
+{
+    fn from(ptr: MutPtr<T, BASE>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> fmt::Debug for AtomicTinyPtr<T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+    }
+}
@@ -5,23 +5,26 @@ use core::{
     fmt,
     hash::{Hash, Hasher},
     marker::{PhantomData, Unsize},
+    mem::MaybeUninit,
     ops::CoerceUnsized,
 };
 
-use crate::{base_ptr, Pointable, PointerConversionError};
+use crate::{base_ptr, Pointable, PointerConversionError, Ref};
 
-use super::MutPtr;
+use super::{offset::cast, MutPtr, NonNull, Offset};
 
 /// A tiny constant pointer
-pub struct ConstPtr<T: Pointable + ?Sized, const BASE: usize> {
-    pub(crate) ptr: u16,
+///
+/// The address is stored as an offset of type `O` from `BASE`, see [`Offset`].
+pub struct ConstPtr<T: Pointable + ?Sized, const BASE: usize, O: Offset = u16> {
+    pub(crate) ptr: O,
     pub(crate) meta: <T as Pointable>::PointerMetaTiny,
     pub(crate) _marker: PhantomData<*const T>,
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> ConstPtr<T, BASE, O> {
     /// Create a new constant pointer from raw parts
-    pub const fn from_raw_parts(ptr: u16, meta: <T as Pointable>::PointerMetaTiny) -> Self {
+    pub const fn from_raw_parts(ptr: O, meta: <T as Pointable>::PointerMetaTiny) -> Self {
         Self {
             ptr,
             meta,
@@ -39,7 +42,7 @@ impl<T: Pointable + ?Sized, const BASE: usize> ConstPtr<T, BASE> {
         } else {
             addr.wrapping_sub(BASE)
         };
-        Self::from_raw_parts(addr as u16, T::tiny_unchecked(meta))
+        Self::from_raw_parts(O::truncate(addr), T::tiny_unchecked(meta))
     }
     /// Tries to create a tiny pointer from a pointer
     ///
@@ -52,27 +55,26 @@ impl<T: Pointable + ?Sized, const BASE: usize> ConstPtr<T, BASE> {
         } else {
             addr.wrapping_sub(BASE)
         };
-        let addr = addr
-            .try_into()
-            .map_err(PointerConversionError::NotInAddressSpace)?;
+        let addr = O::try_from(addr).map_err(PointerConversionError::NotInAddressSpace)?;
         let meta = T::try_tiny(meta).map_err(PointerConversionError::CannotReduceMeta)?;
         Ok(Self::from_raw_parts(addr, meta))
     }
     /// Widens the pointer
     pub fn wide(self) -> *const T {
-        let addr = if self.ptr == 0 {
+        let addr = if self.is_null() {
             0
         } else {
-            usize::from(self.ptr).wrapping_add(BASE)
+            self.ptr.to_usize().wrapping_add(BASE)
         };
         T::create_ptr(base_ptr::<BASE>(), addr, T::huge(self.meta))
     }
     /// Returns `true` if the pointer is null
     pub const fn is_null(self) -> bool {
-        self.ptr == 0
+        // SAFETY: `Option<O::NonZero>` has the layout of `O`, with the null offset as `None`
+        unsafe { cast::<O, Option<O::NonZero>>(self.ptr) }.is_none()
     }
     /// Casts to a pointer of another type
-    pub const fn cast<U: Pointable<PointerMetaTiny = ()>>(self) -> ConstPtr<U, BASE>
+    pub const fn cast<U: Pointable<PointerMetaTiny = ()>>(self) -> ConstPtr<U, BASE, O>
     where
         T: Pointable<PointerMetaTiny = ()>,
     {
@@ -81,117 +83,78 @@ impl<T: Pointable + ?Sized, const BASE: usize> ConstPtr<T, BASE> {
     /// Use the pointer value in a new pointer of another type
     pub const fn with_metadata_of<U: Pointable + ?Sized>(
         self,
-        val: ConstPtr<U, BASE>,
-    ) -> ConstPtr<U, BASE> {
+        val: ConstPtr<U, BASE, O>,
+    ) -> ConstPtr<U, BASE, O> {
         ConstPtr::from_raw_parts(self.ptr, val.meta)
     }
     /// Converts the pointer to mutable
-    pub const fn as_mut(self) -> MutPtr<T, BASE> {
+    pub const fn as_mut(self) -> MutPtr<T, BASE, O> {
         MutPtr::from_raw_parts(self.ptr, self.meta)
     }
     /// Gets the address portion of the pointer
-    pub const fn addr(self) -> u16
+    pub const fn addr(self) -> O
     where
         T: Sized,
     {
         self.ptr
     }
     /// Gets the address portion of the pointer and exposeses the provenenance part
-    pub const fn expose_addr(self) -> u16
+    pub const fn expose_addr(self) -> O
     where
         T: Sized,
     {
         self.ptr
     }
     /// Creates a new pointer with the given address
-    pub const fn with_addr(self, addr: u16) -> Self
+    pub const fn with_addr(self, addr: O) -> Self
     where
         T: Sized,
     {
         Self::from_raw_parts(addr, self.meta)
     }
     /// Creates a new pointer by mapping self’s address to a new one
-    pub fn map_addr(self, f: impl FnOnce(u16) -> u16) -> Self
+    pub fn map_addr(self, f: impl FnOnce(O) -> O) -> Self
     where
         T: Sized,
     {
         self.with_addr(f(self.addr()))
     }
     /// Decompose a pointer into its address and metadata
-    pub fn to_raw_parts(self) -> (ConstPtr<(), BASE>, <T as Pointable>::PointerMetaTiny) {
+    pub fn to_raw_parts(self) -> (ConstPtr<(), BASE, O>, <T as Pointable>::PointerMetaTiny) {
         (ConstPtr::from_raw_parts(self.ptr, ()), self.meta)
     }
-    // TODO: as_ref
-    // TODO: as_ref_unchecked
-    // TODO: as_uninit_ref
-    /// Calculates the offset from a pointer
-    pub const unsafe fn offset(self, count: i16) -> Self
-    where
-        T: Sized,
-    {
-        self.wrapping_offset(count)
-    }
-    /// Calculates the offset from a pointer using wrapping arithmetic
-    pub const fn wrapping_offset(mut self, count: i16) -> Self
-    where
-        T: Sized,
-    {
-        self.ptr = self
-            .ptr
-            .wrapping_add_signed(count.wrapping_mul(core::mem::size_of::<T>() as i16));
-        self
-    }
-    /// Calculates the distance between two pointers
-    pub const unsafe fn offset_from(self, origin: Self) -> i16
-    where
-        T: Sized,
-    {
-        self.wrapping_offset_from(origin)
-    }
-    /// Calculates the distance between two pointers using wrapping arithmetic
-    pub const fn wrapping_offset_from(self, origin: Self) -> i16
-    where
-        T: Sized,
-    {
-        (origin.ptr as i16)
-            .wrapping_sub(self.ptr as i16)
-            .wrapping_div(core::mem::size_of::<T>() as i16)
-    }
-    /// calculates the distance between two pointers where it is known that self is equal or
-    /// greater than origin
-    pub unsafe fn sub_ptr(self, origin: Self) -> u16
-    where
-        T: Sized,
-    {
-        u16::try_from(self.wrapping_offset_from(origin)).unwrap_unchecked()
-    }
-    /// Calculates the offset from a pointer
-    pub const unsafe fn add(self, count: u16) -> Self
-    where
-        T: Sized,
-    {
-        self.offset(count as i16)
-    }
-    /// Calculates the offset from a pointer
-    pub const unsafe fn sub(self, count: u16) -> Self
-    where
-        T: Sized,
-    {
-        self.offset((count as i16).wrapping_neg())
+    /// Returns a tiny reference to the value, or `None` if the pointer is null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to point to a valid value of `T` that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_ref<'a>(self) -> Option<Ref<'a, T, BASE, O>> {
+        NonNull::new(self.as_mut()).map(|ptr| Ref {
+            ptr,
+            _marker: PhantomData,
+        })
     }
-    /// Calculates the offset from a pointer using wrapping arithmetic
-    pub const fn wrapping_add(self, count: u16) -> Self
-    where
-        T: Sized,
-    {
-        self.wrapping_offset(count as i16)
+    /// Returns a tiny reference to the value, without checking for null
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T` that is not mutated for `'a`.
+    pub unsafe fn as_ref_unchecked<'a>(self) -> Ref<'a, T, BASE, O> {
+        Ref {
+            ptr: NonNull::new_unchecked(self.as_mut()),
+            _marker: PhantomData,
+        }
     }
-    /// Calculates the offset from a pointer using wrapping arithmetic
-    pub const fn wrapping_sub(self, count: u16) -> Self
+    /// Returns a tiny reference to the possibly uninitialized value, or `None` if the pointer is
+    /// null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to be aligned and point to memory that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_uninit_ref<'a>(self) -> Option<Ref<'a, MaybeUninit<T>, BASE, O>>
     where
-        T: Sized,
+        T: Pointable<PointerMetaTiny = ()> + Sized,
     {
-        self.wrapping_offset((count as i16).wrapping_neg())
+        self.cast::<MaybeUninit<T>>().as_ref()
     }
     /// Reads the value from self without moving it. this leaves the memory in self unchanged.
     pub unsafe fn read(self) -> T
@@ -214,90 +177,194 @@ impl<T: Pointable + ?Sized, const BASE: usize> ConstPtr<T, BASE> {
     {
         self.wide().read_unaligned()
     }
-    pub unsafe fn copy_to(self, dest: MutPtr<T, BASE>, count: u16)
+    pub unsafe fn copy_to(self, dest: MutPtr<T, BASE, O>, count: O)
     where
         T: Sized,
     {
         dest.copy_from(self, count)
     }
-    pub unsafe fn copy_to_nonoverlapping(self, dest: MutPtr<T, BASE>, count: u16)
+    pub unsafe fn copy_to_nonoverlapping(self, dest: MutPtr<T, BASE, O>, count: O)
     where
         T: Sized,
     {
         dest.copy_from_nonoverlapping(self, count)
     }
-    pub const fn align_offset(self, align: u16) -> u16
+}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, O: Offset> ConstPtr<[T], BASE, O> {
+    pub const fn as_ptr(self) -> ConstPtr<T, BASE, O> {
+        ConstPtr::from_raw_parts(self.ptr, ())
+    }
+    /// Copies all elements of the slice into `dest`
+    ///
+    /// # Panics
+    /// This function panics if `dest` does not have the same length as the slice.
+    ///
+    /// # Safety
+    /// The whole slice must be valid for reads, properly aligned, and must not overlap `dest`.
+    pub unsafe fn copy_to_slice(self, dest: &mut [T])
     where
-        T: Sized,
+        T: Copy,
     {
-        if !align.is_power_of_two() {
-            panic!("align must be a power of two");
-        }
-        (self.ptr.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1))
-            .wrapping_sub(self.ptr)
-            .wrapping_div(core::mem::size_of::<T>() as u16)
+        assert_eq!(
+            usize::from(self.meta),
+            dest.len(),
+            "destination slice length does not match source"
+        );
+        self.as_ptr()
+            .wide()
+            .copy_to_nonoverlapping(dest.as_mut_ptr(), dest.len())
+    }
+    /// Returns a tiny reference to the possibly uninitialized slice, or `None` if the pointer is
+    /// null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to be aligned and point to memory that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_uninit_slice<'a>(self) -> Option<Ref<'a, [MaybeUninit<T>], BASE, O>> {
+        ConstPtr::from_raw_parts(self.ptr, self.meta).as_ref()
     }
 }
 
-impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> ConstPtr<[T], BASE> {
-    pub const fn len(self) -> u16 {
-        self.meta
-    }
-    pub const fn as_ptr(self) -> ConstPtr<T, BASE> {
-        ConstPtr::from_raw_parts(self.ptr, ())
-    }
-    // TODO: as_uninit_slice
+macro_rules! impl_arithmetic {
+    ($($ty:ident, $signed:ident);* $(;)?) => {
+        $(
+            impl<T: Pointable, const BASE: usize> ConstPtr<T, BASE, $ty> {
+                /// Calculates the offset from a pointer
+                pub const unsafe fn offset(self, count: $signed) -> Self {
+                    self.wrapping_offset(count)
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_offset(mut self, count: $signed) -> Self {
+                    self.ptr = self
+                        .ptr
+                        .wrapping_add_signed(count.wrapping_mul(core::mem::size_of::<T>() as $signed));
+                    self
+                }
+                /// Calculates the distance from `origin` to the pointer
+                pub const unsafe fn offset_from(self, origin: Self) -> $signed {
+                    self.wrapping_offset_from(origin)
+                }
+                /// Calculates the distance from `origin` to the pointer using wrapping arithmetic
+                ///
+                /// # Panics
+                /// This function panics if `T` is zero-sized.
+                pub const fn wrapping_offset_from(self, origin: Self) -> $signed {
+                    // Both offsets are in the pool, so their distance in bytes fits in an isize
+                    // but not necessarily in the signed offset type
+                    ((self.ptr as isize - origin.ptr as isize) / core::mem::size_of::<T>() as isize)
+                        as $signed
+                }
+                /// calculates the distance between two pointers where it is known that self is equal or
+                /// greater than origin
+                pub const unsafe fn sub_ptr(self, origin: Self) -> $ty {
+                    (self.ptr.wrapping_sub(origin.ptr) as usize / core::mem::size_of::<T>()) as $ty
+                }
+                /// Calculates the offset from a pointer
+                pub const unsafe fn add(self, count: $ty) -> Self {
+                    self.wrapping_add(count)
+                }
+                /// Calculates the offset from a pointer
+                pub const unsafe fn sub(self, count: $ty) -> Self {
+                    self.wrapping_sub(count)
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_add(mut self, count: $ty) -> Self {
+                    self.ptr = self
+                        .ptr
+                        .wrapping_add(count.wrapping_mul(core::mem::size_of::<T>() as $ty));
+                    self
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_sub(mut self, count: $ty) -> Self {
+                    self.ptr = self
+                        .ptr
+                        .wrapping_sub(count.wrapping_mul(core::mem::size_of::<T>() as $ty));
+                    self
+                }
+                /// Calculates the number of elements to the next multiple of `align`
+                ///
+                /// # Panics
+                /// This function panics if `align` is not a power of two.
+                pub const fn align_offset(self, align: $ty) -> $ty {
+                    if !align.is_power_of_two() {
+                        panic!("align must be a power of two");
+                    }
+                    let bytes = (self.ptr.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1))
+                        .wrapping_sub(self.ptr);
+                    (bytes as usize / core::mem::size_of::<T>()) as $ty
+                }
+            }
+
+            impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> ConstPtr<[T], BASE, $ty> {
+                /// Returns the number of elements in the slice
+                ///
+                /// The length is stored as a `u16`. A `u8` pool has room for at most 255
+                /// elements, so only slices of zero-sized types can be longer.
+                #[allow(clippy::unnecessary_cast)]
+                pub const fn len(self) -> $ty {
+                    self.meta as $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_arithmetic! {
+    u8, i8;
+    u16, i16;
+    u32, i32;
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> PartialEq for ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> PartialEq for ConstPtr<T, BASE, O> {
     fn eq(&self, other: &Self) -> bool {
         (self.ptr == other.ptr) && (self.meta == other.meta)
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Eq for ConstPtr<T, BASE> {}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Eq for ConstPtr<T, BASE, O> {}
 
-impl<T: Pointable + ?Sized, const BASE: usize> Ord for ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Ord for ConstPtr<T, BASE, O> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.ptr.cmp(&other.ptr)
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> PartialOrd for ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> PartialOrd for ConstPtr<T, BASE, O> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<T: Pointable + ?Sized + Unsize<U>, U: Pointable, const BASE: usize>
-    CoerceUnsized<ConstPtr<U, BASE>> for ConstPtr<T, BASE>
+impl<T: Pointable + ?Sized + Unsize<U>, U: Pointable, const BASE: usize, O: Offset>
+    CoerceUnsized<ConstPtr<U, BASE, O>> for ConstPtr<T, BASE, O>
 where
     <T as Pointable>::PointerMetaTiny: CoerceUnsized<<U as Pointable>::PointerMetaTiny>,
 {
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Clone for ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Clone for ConstPtr<T, BASE, O> {
     fn clone(&self) -> Self {
         *self
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> Copy for ConstPtr<T, BASE> {}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Copy for ConstPtr<T, BASE, O> {}
 
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Debug for ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Debug for ConstPtr<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(self, f)
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Hash for ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Hash for ConstPtr<T, BASE, O> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write_usize(BASE);
-        state.write_u16(self.ptr);
+        self.ptr.hash(state);
         self.meta.hash(state);
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Pointer for ConstPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Pointer for ConstPtr<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.wide(), f)
     }
@@ -1,5 +1,7 @@
 //! Raw pointers
 
+mod offset;
+pub use offset::*;
 mod const_ptr;
 #[doc(inline)]
 pub use const_ptr::*;
@@ -9,3 +11,9 @@ mod non_null;
 pub use non_null::*;
 mod unique;
 pub use unique::*;
+mod atomic;
+pub use atomic::*;
+mod tagged;
+pub use tagged::*;
+mod scaled;
+pub use scaled::*;
@@ -5,23 +5,26 @@ use core::{
     fmt,
     hash::{Hash, Hasher},
     marker::{PhantomData, Unsize},
+    mem::MaybeUninit,
     ops::CoerceUnsized,
 };
 
-use crate::{base_ptr_mut, Pointable, PointerConversionError};
+use crate::{base_ptr_mut, Pointable, PointerConversionError, Ref, RefMut};
 
-use super::ConstPtr;
+use super::{offset::cast, ConstPtr, NonNull, Offset};
 
 /// A tiny mutable pointer
-pub struct MutPtr<T: Pointable + ?Sized, const BASE: usize> {
-    pub(crate) ptr: u16,
+///
+/// The address is stored as an offset of type `O` from `BASE`, see [`Offset`].
+pub struct MutPtr<T: Pointable + ?Sized, const BASE: usize, O: Offset = u16> {
+    pub(crate) ptr: O,
     pub(crate) meta: <T as Pointable>::PointerMetaTiny,
     pub(crate) _marker: PhantomData<*const T>,
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> MutPtr<T, BASE, O> {
     /// Create a new constant pointer from raw parts
-    pub const fn from_raw_parts(ptr: u16, meta: <T as Pointable>::PointerMetaTiny) -> Self {
+    pub const fn from_raw_parts(ptr: O, meta: <T as Pointable>::PointerMetaTiny) -> Self {
         Self {
             ptr,
             meta,
@@ -39,7 +42,7 @@ impl<T: Pointable + ?Sized, const BASE: usize> MutPtr<T, BASE> {
         } else {
             addr.wrapping_sub(BASE)
         };
-        Self::from_raw_parts(addr as u16, T::tiny_unchecked(meta))
+        Self::from_raw_parts(O::truncate(addr), T::tiny_unchecked(meta))
     }
     /// Tries to create a tiny pointer from a pointer
     ///
@@ -52,27 +55,26 @@ impl<T: Pointable + ?Sized, const BASE: usize> MutPtr<T, BASE> {
         } else {
             addr.wrapping_sub(BASE)
         };
-        let addr = addr
-            .try_into()
-            .map_err(PointerConversionError::NotInAddressSpace)?;
+        let addr = O::try_from(addr).map_err(PointerConversionError::NotInAddressSpace)?;
         let meta = T::try_tiny(meta).map_err(PointerConversionError::CannotReduceMeta)?;
         Ok(Self::from_raw_parts(addr, meta))
     }
     /// Widens the pointer
     pub fn wide(self) -> *mut T {
-        let addr = if self.ptr == 0 {
+        let addr = if self.is_null() {
             0
         } else {
-            usize::from(self.ptr).wrapping_add(BASE)
+            self.ptr.to_usize().wrapping_add(BASE)
         };
         T::create_ptr_mut(base_ptr_mut::<BASE>(), addr, T::huge(self.meta))
     }
     /// Returns `true` if the pointer is null
     pub const fn is_null(self) -> bool {
-        self.ptr == 0
+        // SAFETY: `Option<O::NonZero>` has the layout of `O`, with the null offset as `None`
+        unsafe { cast::<O, Option<O::NonZero>>(self.ptr) }.is_none()
     }
     /// Casts to a pointer of another type
-    pub const fn cast<U: Pointable<PointerMetaTiny = ()>>(self) -> MutPtr<U, BASE>
+    pub const fn cast<U: Pointable<PointerMetaTiny = ()>>(self) -> MutPtr<U, BASE, O>
     where
         T: Pointable<PointerMetaTiny = ()>,
     {
@@ -81,119 +83,105 @@ impl<T: Pointable + ?Sized, const BASE: usize> MutPtr<T, BASE> {
     /// Use the pointer value in a new pointer of another type
     pub const fn with_metadata_of<U: Pointable + ?Sized>(
         self,
-        val: MutPtr<U, BASE>,
-    ) -> MutPtr<U, BASE> {
+        val: MutPtr<U, BASE, O>,
+    ) -> MutPtr<U, BASE, O> {
         MutPtr::from_raw_parts(self.ptr, val.meta)
     }
-    pub const fn as_const(self) -> ConstPtr<T, BASE> {
+    pub const fn as_const(self) -> ConstPtr<T, BASE, O> {
         ConstPtr::from_raw_parts(self.ptr, self.meta)
     }
     /// Gets the address portion of the pointer
-    pub const fn addr(self) -> u16
+    pub const fn addr(self) -> O
     where
         T: Sized,
     {
         self.ptr
     }
     /// Gets the address portion of the pointer and exposeses the provenenance part
-    pub const fn expose_addr(self) -> u16
+    pub const fn expose_addr(self) -> O
     where
         T: Sized,
     {
         self.ptr
     }
     /// Creates a new pointer with the given address
-    pub const fn with_addr(self, addr: u16) -> Self
+    pub const fn with_addr(self, addr: O) -> Self
     where
         T: Sized,
     {
         Self::from_raw_parts(addr, self.meta)
     }
     /// Creates a new pointer by mapping self’s address to a new one
-    pub fn map_addr(self, f: impl FnOnce(u16) -> u16) -> Self
+    pub fn map_addr(self, f: impl FnOnce(O) -> O) -> Self
     where
         T: Sized,
     {
         self.with_addr(f(self.addr()))
     }
     /// Decompose a pointer into its address and metadata
-    pub fn to_raw_parts(self) -> (ConstPtr<(), BASE>, <T as Pointable>::PointerMetaTiny) {
+    pub fn to_raw_parts(self) -> (ConstPtr<(), BASE, O>, <T as Pointable>::PointerMetaTiny) {
         (ConstPtr::from_raw_parts(self.ptr, ()), self.meta)
     }
-    // TODO: as_ref
-    // TODO: as_ref_unchecked
-    // TODO: as_uninit_ref
-    /// Calculates the offset from a pointer
-    pub const unsafe fn offset(self, count: i16) -> Self
-    where
-        T: Sized,
-    {
-        self.wrapping_offset(count)
-    }
-    /// Calculates the offset from a pointer using wrapping arithmetic
-    pub const fn wrapping_offset(mut self, count: i16) -> Self
-    where
-        T: Sized,
-    {
-        self.ptr = self
-            .ptr
-            .wrapping_add_signed(count.wrapping_mul(core::mem::size_of::<T>() as i16));
-        self
-    }
-    // TODO: as_mut
-    // TODO: as_mut_unchecked
-    // TODO: as_uninit_mut
-    /// Calculates the distance between two pointers
-    pub const unsafe fn offset_from(self, origin: Self) -> i16
-    where
-        T: Sized,
-    {
-        self.wrapping_offset_from(origin)
-    }
-    /// Calculates the distance between two pointers using wrapping arithmetic
-    pub const fn wrapping_offset_from(self, origin: Self) -> i16
-    where
-        T: Sized,
-    {
-        (origin.ptr as i16)
-            .wrapping_sub(self.ptr as i16)
-            .wrapping_div(core::mem::size_of::<T>() as i16)
+    /// Returns a tiny reference to the value, or `None` if the pointer is null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to point to a valid value of `T` that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_ref<'a>(self) -> Option<Ref<'a, T, BASE, O>> {
+        self.as_const().as_ref()
     }
-    /// calculates the distance between two pointers where it is known that self is equal or
-    /// greater than origin
-    pub unsafe fn sub_ptr(self, origin: Self) -> u16
-    where
-        T: Sized,
-    {
-        u16::try_from(self.wrapping_offset_from(origin)).unwrap_unchecked()
+    /// Returns a tiny reference to the value, without checking for null
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T` that is not mutated for `'a`.
+    pub unsafe fn as_ref_unchecked<'a>(self) -> Ref<'a, T, BASE, O> {
+        self.as_const().as_ref_unchecked()
     }
-    /// Calculates the offset from a pointer
-    pub const unsafe fn add(self, count: u16) -> Self
+    /// Returns a tiny reference to the possibly uninitialized value, or `None` if the pointer is
+    /// null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to be aligned and point to memory that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_uninit_ref<'a>(self) -> Option<Ref<'a, MaybeUninit<T>, BASE, O>>
     where
-        T: Sized,
+        T: Pointable<PointerMetaTiny = ()> + Sized,
     {
-        self.offset(count as i16)
+        self.as_const().as_uninit_ref()
     }
-    /// Calculates the offset from a pointer
-    pub const unsafe fn sub(self, count: u16) -> Self
-    where
-        T: Sized,
-    {
-        self.offset((count as i16).wrapping_neg())
+    /// Returns a mutable tiny reference to the value, or `None` if the pointer is null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to point to a valid value of `T` that is not accessed
+    /// through any other pointer for `'a`.
+    pub unsafe fn as_mut<'a>(self) -> Option<RefMut<'a, T, BASE, O>> {
+        NonNull::new(self).map(|ptr| RefMut {
+            ptr,
+            _marker: PhantomData,
+        })
     }
-    /// Calculates the offset from a pointer using wrapping arithmetic
-    pub const fn wrapping_add(self, count: u16) -> Self
-    where
-        T: Sized,
-    {
-        self.wrapping_offset(count as i16)
+    /// Returns a mutable tiny reference to the value, without checking for null
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T` that is not accessed through any other
+    /// pointer for `'a`.
+    pub unsafe fn as_mut_unchecked<'a>(self) -> RefMut<'a, T, BASE, O> {
+        RefMut {
+            ptr: NonNull::new_unchecked(self),
+            _marker: PhantomData,
+        }
     }
-    /// Calculates the offset from a pointer using wrapping arithmetic
-    pub const fn wrapping_sub(self, count: u16) -> Self
+    /// Returns a mutable tiny reference to the possibly uninitialized value, or `None` if the
+    /// pointer is null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to be aligned and point to memory that is not accessed
+    /// through any other pointer for `'a`.
+    pub unsafe fn as_uninit_mut<'a>(self) -> Option<RefMut<'a, MaybeUninit<T>, BASE, O>>
     where
-        T: Sized,
+        T: Pointable<PointerMetaTiny = ()> + Sized,
     {
-        self.wrapping_offset((count as i16).wrapping_neg())
+        self.cast::<MaybeUninit<T>>().as_mut()
     }
     /// Reads the value from self without moving it. this leaves the memory in self unchanged.
     pub unsafe fn read(self) -> T
@@ -217,36 +205,36 @@ impl<T: Pointable + ?Sized, const BASE: usize> MutPtr<T, BASE> {
         self.wide().read_unaligned()
     }
     /// Copies count * size_of<T> bytes from self to dest. the source nad destination may overlap
-    pub unsafe fn copy_to(self, dest: MutPtr<T, BASE>, count: u16)
+    pub unsafe fn copy_to(self, dest: MutPtr<T, BASE, O>, count: O)
     where
         T: Sized,
     {
-        self.wide().copy_to(dest.wide(), count as usize)
+        self.wide().copy_to(dest.wide(), count.to_usize())
     }
     /// Copies count * size_of<T> bytes from self to dest. The source and destination may *not*
     /// overlap.
-    pub unsafe fn copy_to_nonoverlapping(self, dest: MutPtr<T, BASE>, count: u16)
+    pub unsafe fn copy_to_nonoverlapping(self, dest: MutPtr<T, BASE, O>, count: O)
     where
         T: Sized,
     {
         self.wide()
-            .copy_to_nonoverlapping(dest.wide(), count as usize)
+            .copy_to_nonoverlapping(dest.wide(), count.to_usize())
     }
     /// Copies count * size_of<T> bytes from src to self. the source and destination may overlap
-    pub unsafe fn copy_from(self, src: ConstPtr<T, BASE>, count: u16)
+    pub unsafe fn copy_from(self, src: ConstPtr<T, BASE, O>, count: O)
     where
         T: Sized,
     {
-        self.wide().copy_from(src.wide(), count as usize)
+        self.wide().copy_from(src.wide(), count.to_usize())
     }
     /// Copies count * size_of<T> bytes from src to self. the source and destination may *not*
     /// overlap
-    pub unsafe fn copy_from_nonoverlapping(self, src: ConstPtr<T, BASE>, count: u16)
+    pub unsafe fn copy_from_nonoverlapping(self, src: ConstPtr<T, BASE, O>, count: O)
     where
         T: Sized,
     {
         self.wide()
-            .copy_from_nonoverlapping(src.wide(), count as usize)
+            .copy_from_nonoverlapping(src.wide(), count.to_usize())
     }
     /// Executes any destructor of the pointed-to value
     pub unsafe fn drop_in_place(self) {
@@ -261,11 +249,11 @@ impl<T: Pointable + ?Sized, const BASE: usize> MutPtr<T, BASE> {
     }
     /// Invokes a memset on the specified pointer, setting count * size_of::<T>() bytes of memory
     /// starting at self to val
-    pub unsafe fn write_bytes(self, val: u8, count: u16)
+    pub unsafe fn write_bytes(self, val: u8, count: O)
     where
         T: Sized,
     {
-        self.wide().write_bytes(val, count as usize)
+        self.wide().write_bytes(val, count.to_usize())
     }
     /// Performs a volatile write of a memory location
     pub unsafe fn write_volatile(self, val: T)
@@ -290,86 +278,205 @@ impl<T: Pointable + ?Sized, const BASE: usize> MutPtr<T, BASE> {
     }
 
     /// Swaps the values at two mutable locations
-    pub unsafe fn swap(self, with: MutPtr<T, BASE>)
+    pub unsafe fn swap(self, with: MutPtr<T, BASE, O>)
     where
         T: Sized,
     {
         self.wide().swap(with.wide())
     }
+}
 
-    pub const fn align_offset(self, align: u16) -> u16
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, O: Offset> MutPtr<[T], BASE, O> {
+    pub const fn as_mut_ptr(self) -> MutPtr<T, BASE, O> {
+        MutPtr::from_raw_parts(self.ptr, ())
+    }
+    /// Writes `val` to every element of the slice, without dropping the old values
+    ///
+    /// # Safety
+    /// The whole slice must be valid for writes and properly aligned.
+    pub unsafe fn fill(self, val: T)
     where
-        T: Sized,
+        T: Clone,
     {
-        if !align.is_power_of_two() {
-            panic!("align must be a power of two");
+        let ptr = self.as_mut_ptr().wide();
+        for i in 0..usize::from(self.meta) {
+            ptr.add(i).write(val.clone());
         }
-        (self.ptr.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1))
-            .wrapping_sub(self.ptr)
-            .wrapping_div(core::mem::size_of::<T>() as u16)
     }
-}
-
-impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> MutPtr<[T], BASE> {
-    pub const fn len(self) -> u16 {
-        self.meta
+    /// Copies all elements from `src` into the slice
+    ///
+    /// # Panics
+    /// This function panics if `src` does not have the same length as the slice.
+    ///
+    /// # Safety
+    /// The whole slice must be valid for writes, properly aligned, and must not overlap `src`.
+    pub unsafe fn copy_from_slice(self, src: &[T])
+    where
+        T: Copy,
+    {
+        assert_eq!(
+            usize::from(self.meta),
+            src.len(),
+            "source slice length does not match destination"
+        );
+        self.as_mut_ptr()
+            .wide()
+            .copy_from_nonoverlapping(src.as_ptr(), src.len())
+    }
+    /// Copies all elements of the slice into `dest`
+    ///
+    /// # Panics
+    /// This function panics if `dest` does not have the same length as the slice.
+    ///
+    /// # Safety
+    /// The whole slice must be valid for reads, properly aligned, and must not overlap `dest`.
+    pub unsafe fn copy_to_slice(self, dest: &mut [T])
+    where
+        T: Copy,
+    {
+        self.as_const().copy_to_slice(dest)
     }
-    pub const fn as_mut_ptr(self) -> MutPtr<T, BASE> {
-        MutPtr::from_raw_parts(self.ptr, ())
+    /// Returns a tiny reference to the possibly uninitialized slice, or `None` if the pointer is
+    /// null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to be aligned and point to memory that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_uninit_slice<'a>(self) -> Option<Ref<'a, [MaybeUninit<T>], BASE, O>> {
+        self.as_const().as_uninit_slice()
     }
-    // TODO: as_uninit_slice
-    // TODO: as_uninit_slice_mut
+    /// Returns a mutable tiny reference to the possibly uninitialized slice, or `None` if the
+    /// pointer is null
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to be aligned and point to memory that is not accessed
+    /// through any other pointer for `'a`.
+    pub unsafe fn as_uninit_slice_mut<'a>(self) -> Option<RefMut<'a, [MaybeUninit<T>], BASE, O>> {
+        MutPtr::from_raw_parts(self.ptr, self.meta).as_mut()
+    }
+}
+
+macro_rules! impl_arithmetic {
+    ($($ty:ident, $signed:ident);* $(;)?) => {
+        $(
+            impl<T: Pointable, const BASE: usize> MutPtr<T, BASE, $ty> {
+                /// Calculates the offset from a pointer
+                pub const unsafe fn offset(self, count: $signed) -> Self {
+                    self.wrapping_offset(count)
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_offset(self, count: $signed) -> Self {
+                    self.as_const().wrapping_offset(count).as_mut()
+                }
+                /// Calculates the distance from `origin` to the pointer
+                pub const unsafe fn offset_from(self, origin: Self) -> $signed {
+                    self.wrapping_offset_from(origin)
+                }
+                /// Calculates the distance from `origin` to the pointer using wrapping arithmetic
+                ///
+                /// # Panics
+                /// This function panics if `T` is zero-sized.
+                pub const fn wrapping_offset_from(self, origin: Self) -> $signed {
+                    self.as_const().wrapping_offset_from(origin.as_const())
+                }
+                /// calculates the distance between two pointers where it is known that self is equal or
+                /// greater than origin
+                pub const unsafe fn sub_ptr(self, origin: Self) -> $ty {
+                    self.as_const().sub_ptr(origin.as_const())
+                }
+                /// Calculates the offset from a pointer
+                pub const unsafe fn add(self, count: $ty) -> Self {
+                    self.wrapping_add(count)
+                }
+                /// Calculates the offset from a pointer
+                pub const unsafe fn sub(self, count: $ty) -> Self {
+                    self.wrapping_sub(count)
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_add(self, count: $ty) -> Self {
+                    self.as_const().wrapping_add(count).as_mut()
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_sub(self, count: $ty) -> Self {
+                    self.as_const().wrapping_sub(count).as_mut()
+                }
+                /// Calculates the number of elements to the next multiple of `align`
+                ///
+                /// # Panics
+                /// This function panics if `align` is not a power of two.
+                pub const fn align_offset(self, align: $ty) -> $ty {
+                    self.as_const().align_offset(align)
+                }
+            }
+
+            impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> MutPtr<[T], BASE, $ty> {
+                /// Returns the number of elements in the slice
+                ///
+                /// The length is stored as a `u16`. A `u8` pool has room for at most 255
+                /// elements, so only slices of zero-sized types can be longer.
+                pub const fn len(self) -> $ty {
+                    self.as_const().len()
+                }
+            }
+        )*
+    };
+}
+
+impl_arithmetic! {
+    u8, i8;
+    u16, i16;
+    u32, i32;
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> PartialEq for MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> PartialEq for MutPtr<T, BASE, O> {
     fn eq(&self, other: &Self) -> bool {
         (self.ptr == other.ptr) && (self.meta == other.meta)
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Eq for MutPtr<T, BASE> {}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Eq for MutPtr<T, BASE, O> {}
 
-impl<T: Pointable + ?Sized, const BASE: usize> Ord for MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Ord for MutPtr<T, BASE, O> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.ptr.cmp(&other.ptr)
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> PartialOrd for MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> PartialOrd for MutPtr<T, BASE, O> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<T: Pointable + ?Sized + Unsize<U>, U: Pointable, const BASE: usize>
-    CoerceUnsized<MutPtr<U, BASE>> for MutPtr<T, BASE>
+impl<T: Pointable + ?Sized + Unsize<U>, U: Pointable, const BASE: usize, O: Offset>
+    CoerceUnsized<MutPtr<U, BASE, O>> for MutPtr<T, BASE, O>
 where
     <T as Pointable>::PointerMetaTiny: CoerceUnsized<<U as Pointable>::PointerMetaTiny>,
 {
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Clone for MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Clone for MutPtr<T, BASE, O> {
     fn clone(&self) -> Self {
         *self
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> Copy for MutPtr<T, BASE> {}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Copy for MutPtr<T, BASE, O> {}
 
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Debug for MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Debug for MutPtr<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(self, f)
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Hash for MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Hash for MutPtr<T, BASE, O> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write_usize(BASE);
-        state.write_u16(self.ptr);
+        self.ptr.hash(state);
         self.meta.hash(state);
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Pointer for MutPtr<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Pointer for MutPtr<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.wide(), f)
     }
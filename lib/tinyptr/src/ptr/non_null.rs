@@ -1,159 +1,288 @@
-use core::{num::NonZeroU16, marker::{PhantomData, Unsize}, ops::CoerceUnsized, fmt, cmp::Ordering, hash};
+use core::{
+    cmp::Ordering,
+    fmt, hash,
+    marker::{PhantomData, Unsize},
+    mem::MaybeUninit,
+    ops::CoerceUnsized,
+};
 
-use crate::Pointable;
+use crate::{Pointable, Ref, RefMut};
 
-use super::{MutPtr, Unique};
+use super::{offset::cast, MutPtr, Offset, Unique};
 
 /// `*mut T` but non-zero and covariant
-pub struct NonNull<T: Pointable + ?Sized, const BASE: usize> {
-    pub(crate) ptr: NonZeroU16,
+///
+/// The address is a non-zero offset at offset 0, so `Option<NonNull<T, BASE>>` has the same size
+/// as `NonNull<T, BASE>`, like it does for `Unique`, `Ref` and `RefMut`.
+#[repr(C)]
+pub struct NonNull<T: Pointable + ?Sized, const BASE: usize, O: Offset = u16> {
+    pub(crate) ptr: O::NonZero,
     pub(crate) meta: <T as Pointable>::PointerMetaTiny,
-    pub(crate) _marker: PhantomData<MutPtr<T, BASE>>
+    pub(crate) _marker: PhantomData<MutPtr<T, BASE, O>>,
 }
 
-impl<T: Pointable<PointerMetaTiny = ()> + Sized, const BASE: usize> NonNull<T, BASE> {
-    /// Creates a dangling but well-aligned `NonNull`
-    pub const fn dangling() -> Self {
-        // SAFE: align_of is never 0
-        unsafe {
-            Self::new_unchecked(MutPtr::from_raw_parts(core::mem::align_of::<T>() as u16, ()))
-        }
+macro_rules! impl_dangling {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: Pointable<PointerMetaTiny = ()> + Sized, const BASE: usize> NonNull<T, BASE, $ty> {
+                /// Creates a dangling but well-aligned `NonNull`
+                ///
+                /// # Panics
+                /// This function panics if the alignment of `T` does not fit into the offset.
+                pub const fn dangling() -> Self {
+                    let align = core::mem::align_of::<T>();
+                    assert!(align <= $ty::MAX as usize, "alignment does not fit into the offset");
+                    // SAFETY: alignments are not 0
+                    unsafe { Self::new_unchecked(MutPtr::from_raw_parts(align as $ty, ())) }
+                }
+            }
+
+            impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> NonNull<[T], BASE, $ty> {
+                /// Returns the number of elements in the slice
+                pub const fn len(self) -> $ty {
+                    self.as_ptr().len()
+                }
+            }
+        )*
+    };
+}
+
+impl_dangling!(u8, u16, u32);
+
+impl<T: Pointable<PointerMetaTiny = ()> + Sized, const BASE: usize, O: Offset> NonNull<T, BASE, O> {
+    /// Returns a tiny reference to the possibly uninitialized value
+    ///
+    /// # Safety
+    /// The pointer has to be aligned and point to memory that is not mutated for `'a`.
+    pub unsafe fn as_uninit_ref<'a>(&self) -> Ref<'a, MaybeUninit<T>, BASE, O> {
+        self.cast().as_ref()
+    }
+    /// Returns a mutable tiny reference to the possibly uninitialized value
+    ///
+    /// # Safety
+    /// The pointer has to be aligned and point to memory that is not accessed through any other
+    /// pointer for `'a`.
+    pub unsafe fn as_uninit_mut<'a>(&mut self) -> RefMut<'a, MaybeUninit<T>, BASE, O> {
+        self.cast().as_mut()
     }
-    // TODO: as_uninit_ref
-    // TODO: as_uninit_mut
 }
-impl<T: Pointable + ?Sized, const BASE: usize> NonNull<T, BASE> {
-    pub const unsafe fn new_unchecked(ptr: MutPtr<T, BASE>) -> Self {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> NonNull<T, BASE, O> {
+    pub const unsafe fn new_unchecked(ptr: MutPtr<T, BASE, O>) -> Self {
         NonNull {
-            ptr: NonZeroU16::new_unchecked(ptr.ptr),
+            // SAFETY: `O::NonZero` has the layout of `O`, and the caller ensures it is not null
+            ptr: cast::<O, O::NonZero>(ptr.ptr),
             meta: ptr.meta,
-            _marker: PhantomData
+            _marker: PhantomData,
         }
     }
-    pub const fn new(ptr: MutPtr<T, BASE>) -> Option<Self> {
-        if ptr.is_null() {
-            None
-        } else {
-            // SAFETY: Just checked for null
-            unsafe {
-                Some(Self::new_unchecked(ptr))
-            }
+    pub const fn new(ptr: MutPtr<T, BASE, O>) -> Option<Self> {
+        // SAFETY: `Option<O::NonZero>` has the layout of `O`, with the null offset as `None`
+        match unsafe { cast::<O, Option<O::NonZero>>(ptr.ptr) } {
+            Some(addr) => Some(NonNull {
+                ptr: addr,
+                meta: ptr.meta,
+                _marker: PhantomData,
+            }),
+            None => None,
         }
     }
     pub const fn from_raw_parts(
-        data_address: NonNull<(), BASE>,
-        metadata: <T as Pointable>::PointerMetaTiny
+        data_address: NonNull<(), BASE, O>,
+        metadata: <T as Pointable>::PointerMetaTiny,
     ) -> Self {
-        unsafe {
-            Self::new_unchecked(MutPtr::from_raw_parts(data_address.as_ptr().addr(), metadata))
+        NonNull {
+            ptr: data_address.ptr,
+            meta: metadata,
+            _marker: PhantomData,
         }
     }
-    pub const fn to_raw_parts(self) -> (NonNull<(), BASE>, <T as Pointable>::PointerMetaTiny) {
+    pub const fn to_raw_parts(self) -> (NonNull<(), BASE, O>, <T as Pointable>::PointerMetaTiny) {
         (self.cast(), self.meta)
     }
-    pub const fn addr(self) -> NonZeroU16 {
+    pub const fn addr(self) -> O::NonZero {
         self.ptr
     }
-    pub const fn with_addr(self, addr: NonZeroU16) -> Self
+    pub const fn with_addr(self, addr: O::NonZero) -> Self
     where
-        T: Sized
+        T: Sized,
     {
         Self {
             ptr: addr,
             meta: self.meta,
-            _marker: PhantomData
+            _marker: PhantomData,
         }
     }
-    pub fn map_addr(self, f: impl FnOnce(NonZeroU16) -> NonZeroU16) -> Self
-    where T: Sized
+    pub fn map_addr(self, f: impl FnOnce(O::NonZero) -> O::NonZero) -> Self
+    where
+        T: Sized,
     {
         self.with_addr(f(self.addr()))
     }
-    pub const fn as_ptr(self) -> MutPtr<T, BASE> {
-        MutPtr::from_raw_parts(self.ptr.get(), self.meta)
+    pub const fn as_ptr(self) -> MutPtr<T, BASE, O> {
+        // SAFETY: `O::NonZero` has the layout of `O`
+        MutPtr::from_raw_parts(unsafe { cast::<O::NonZero, O>(self.ptr) }, self.meta)
     }
-    // TODO: as_ref
-    // TODO: as_mut
-    pub const fn cast<U>(self) -> NonNull<U, BASE>
-    where U: Pointable<PointerMetaTiny = ()>
+    /// Returns a tiny reference to the value
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T` that is not mutated for `'a`.
+    pub unsafe fn as_ref<'a>(&self) -> Ref<'a, T, BASE, O> {
+        Ref {
+            ptr: *self,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns a mutable tiny reference to the value
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T` that is not accessed through any other
+    /// pointer for `'a`.
+    pub unsafe fn as_mut<'a>(&mut self) -> RefMut<'a, T, BASE, O> {
+        RefMut {
+            ptr: *self,
+            _marker: PhantomData,
+        }
+    }
+    pub const fn cast<U>(self) -> NonNull<U, BASE, O>
+    where
+        U: Pointable<PointerMetaTiny = ()>,
     {
         NonNull {
             ptr: self.ptr,
             meta: (),
-            _marker: PhantomData
+            _marker: PhantomData,
         }
     }
 }
 
-impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize> NonNull<[T], BASE> {
-    pub const fn slice_from_raw_parts(data: NonNull<T, BASE>, len: u16) -> Self {
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, O: Offset> NonNull<[T], BASE, O> {
+    pub const fn slice_from_raw_parts(data: NonNull<T, BASE, O>, len: u16) -> Self {
         Self {
             ptr: data.ptr,
             meta: len,
-            _marker: PhantomData
+            _marker: PhantomData,
         }
     }
-    pub const fn len(self) -> u16 {
-        self.meta
-    }
-    pub const fn as_non_null_ptr(self) -> NonNull<T, BASE> {
+    pub const fn as_non_null_ptr(self) -> NonNull<T, BASE, O> {
         NonNull {
             ptr: self.ptr,
             meta: (),
-            _marker: PhantomData
+            _marker: PhantomData,
         }
     }
-    pub const fn as_mut_ptr(self) -> MutPtr<T, BASE> {
+    pub const fn as_mut_ptr(self) -> MutPtr<T, BASE, O> {
         self.as_non_null_ptr().as_ptr()
     }
-    // TODO: as_uninit_slice
-    // TODO: as_uninit_slice_mut
+    /// Returns a tiny reference to the possibly uninitialized slice
+    ///
+    /// # Safety
+    /// The pointer has to be aligned and point to memory that is not mutated for `'a`.
+    pub unsafe fn as_uninit_slice<'a>(&self) -> Ref<'a, [MaybeUninit<T>], BASE, O> {
+        Ref {
+            ptr: NonNull {
+                ptr: self.ptr,
+                meta: self.meta,
+                _marker: PhantomData,
+            },
+            _marker: PhantomData,
+        }
+    }
+    /// Returns a mutable tiny reference to the possibly uninitialized slice
+    ///
+    /// # Safety
+    /// The pointer has to be aligned and point to memory that is not accessed through any other
+    /// pointer for `'a`.
+    pub unsafe fn as_uninit_slice_mut<'a>(&mut self) -> RefMut<'a, [MaybeUninit<T>], BASE, O> {
+        RefMut {
+            ptr: NonNull {
+                ptr: self.ptr,
+                meta: self.meta,
+                _marker: PhantomData,
+            },
+            _marker: PhantomData,
+        }
+    }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Clone for NonNull<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Clone for NonNull<T, BASE, O> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Copy for NonNull<T, BASE> {}
-impl<T: Pointable + ?Sized, U: Pointable + ?Sized, const BASE: usize> CoerceUnsized<NonNull<U, BASE>> for NonNull<T, BASE> where T: Unsize<U>, <T as Pointable>::PointerMetaTiny: CoerceUnsized<<U as Pointable>::PointerMetaTiny> {}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Copy for NonNull<T, BASE, O> {}
+impl<T: Pointable + ?Sized, U: Pointable + ?Sized, const BASE: usize, O: Offset>
+    CoerceUnsized<NonNull<U, BASE, O>> for NonNull<T, BASE, O>
+where
+    T: Unsize<U>,
+    <T as Pointable>::PointerMetaTiny: CoerceUnsized<<U as Pointable>::PointerMetaTiny>,
+{
+}
 
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Debug for NonNull<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Debug for NonNull<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.as_ptr(), f)
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Pointer for NonNull<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Pointer for NonNull<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.as_ptr(), f)
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> Eq for NonNull<T, BASE> {}
-impl<T: Pointable + ?Sized, const BASE: usize> PartialEq for NonNull<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Eq for NonNull<T, BASE, O> {}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> PartialEq for NonNull<T, BASE, O> {
     fn eq(&self, other: &Self) -> bool {
         self.as_ptr() == other.as_ptr()
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> Ord for NonNull<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Ord for NonNull<T, BASE, O> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.as_ptr().cmp(&other.as_ptr())
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> PartialOrd for NonNull<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> PartialOrd for NonNull<T, BASE, O> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         self.as_ptr().partial_cmp(&other.as_ptr())
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> hash::Hash for NonNull<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> hash::Hash for NonNull<T, BASE, O> {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         self.as_ptr().hash(state)
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> From<Unique<T, BASE>> for NonNull<T, BASE> {
-    fn from(ptr: Unique<T, BASE>) -> Self {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> From<Unique<T, BASE, O>>
+    for NonNull<T, BASE, O>
+{
+    fn from(ptr: Unique<T, BASE, O>) -> Self {
         ptr.pointer
     }
 }
-// TODO: From<RefMut<T>>
-// TODO: From<Ref<T>>
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> From<RefMut<'_, T, BASE, O>>
+    for NonNull<T, BASE, O>
+{
+    fn from(reference: RefMut<'_, T, BASE, O>) -> Self {
+        reference.ptr
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> From<Ref<'_, T, BASE, O>>
+    for NonNull<T, BASE, O>
+{
+    fn from(reference: Ref<'_, T, BASE, O>) -> Self {
+        reference.ptr
+    }
+}
+
+// Keep the null pointer optimization from regressing, free list nodes rely on it
+const _: () = {
+    use core::mem::size_of;
+    assert!(size_of::<NonNull<u32, 0>>() == 2);
+    assert!(size_of::<Option<NonNull<u32, 0>>>() == 2);
+    assert!(size_of::<NonNull<[u32], 0>>() == 4);
+    assert!(size_of::<Option<NonNull<[u32], 0>>>() == 4);
+    assert!(size_of::<Option<Unique<u32, 0>>>() == 2);
+    assert!(size_of::<Option<Unique<[u32], 0>>>() == 4);
+    assert!(size_of::<Option<Ref<'static, u32, 0>>>() == 2);
+    assert!(size_of::<Option<RefMut<'static, [u32], 0>>>() == 4);
+    assert!(size_of::<Option<NonNull<u32, 0, u8>>>() == 1);
+    assert!(size_of::<Option<NonNull<u32, 0, u32>>>() == 4);
+};
@@ -0,0 +1,140 @@
+//! Offset types
+//!
+//! Tiny pointers store their address as an offset from the base of their pool. The offset type
+//! defaults to `u16`, which addresses 64 kiB. A 256 byte scratch pool can use `u8` offsets
+//! instead, and external RAM larger than 64 kiB can use `u32` offsets.
+//!
+//! Trait methods cannot be called in `const fn`s, so the pointer arithmetic is implemented for
+//! each offset type separately, and the rest relies on the layout guarantees of [`Offset`].
+
+use core::{
+    fmt,
+    hash::Hash,
+    num::{NonZeroU16, NonZeroU32, NonZeroU8, TryFromIntError},
+};
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Integer type that stores the address of a tiny pointer
+///
+/// This trait is sealed. `NonZero` has the same layout as the offset, and `Option<NonZero>` uses
+/// the null offset as `None`.
+pub trait Offset:
+    Copy + Eq + Ord + Hash + fmt::Debug + TryFrom<usize, Error = TryFromIntError> + private::Sealed
+{
+    /// The non-zero version of the offset, which gives [`NonNull`](super::NonNull) its niche
+    type NonZero: Copy + Eq + Ord + Hash + fmt::Debug;
+    /// The signed version of the offset, used for distances between pointers
+    type Signed: Copy + Eq + Ord + Hash + fmt::Debug;
+
+    /// The null offset
+    const NULL: Self;
+    /// The largest offset
+    const MAX: usize;
+
+    /// Converts the offset into an address offset
+    fn to_usize(self) -> usize;
+    /// Converts an address offset into an offset, discarding the upper bits
+    fn truncate(offset: usize) -> Self;
+    /// Returns the non-zero version of the offset, or `None` if it is null
+    fn to_non_zero(self) -> Option<Self::NonZero>;
+    /// Returns the non-zero version of the offset without checking it
+    ///
+    /// # Safety
+    /// The offset must not be null.
+    unsafe fn to_non_zero_unchecked(self) -> Self::NonZero;
+    /// Converts a non-zero offset back
+    fn from_non_zero(offset: Self::NonZero) -> Self;
+    /// Reinterprets a signed distance as an unsigned one
+    fn from_signed(count: Self::Signed) -> Self;
+    /// Moves the offset by `count` objects of `size` bytes, wrapping around
+    fn wrapping_offset(self, count: Self::Signed, size: usize) -> Self;
+    /// Moves the offset forward by `count` objects of `size` bytes, wrapping around
+    fn wrapping_add(self, count: Self, size: usize) -> Self;
+    /// Moves the offset backward by `count` objects of `size` bytes, wrapping around
+    fn wrapping_sub(self, count: Self, size: usize) -> Self;
+    /// Calculates the distance from `origin` to the offset in objects of `size` bytes
+    fn wrapping_offset_from(self, origin: Self, size: usize) -> Self::Signed;
+    /// Calculates the number of objects of `size` bytes to the next multiple of `align`
+    ///
+    /// # Panics
+    /// This function panics if `align` is not a power of two.
+    fn align_offset(self, align: Self, size: usize) -> Self;
+}
+
+macro_rules! impl_offset {
+    ($($ty:ident, $signed:ident, $non_zero:ident);* $(;)?) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl Offset for $ty {
+                type NonZero = $non_zero;
+                type Signed = $signed;
+
+                const NULL: Self = 0;
+                const MAX: usize = $ty::MAX as usize;
+
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+                fn truncate(offset: usize) -> Self {
+                    offset as $ty
+                }
+                fn to_non_zero(self) -> Option<$non_zero> {
+                    $non_zero::new(self)
+                }
+                unsafe fn to_non_zero_unchecked(self) -> $non_zero {
+                    $non_zero::new_unchecked(self)
+                }
+                fn from_non_zero(offset: $non_zero) -> Self {
+                    offset.get()
+                }
+                fn from_signed(count: $signed) -> Self {
+                    count as $ty
+                }
+                fn wrapping_offset(self, count: $signed, size: usize) -> Self {
+                    $ty::wrapping_add_signed(self, count.wrapping_mul(size as $signed))
+                }
+                fn wrapping_add(self, count: Self, size: usize) -> Self {
+                    $ty::wrapping_add(self, count.wrapping_mul(size as $ty))
+                }
+                fn wrapping_sub(self, count: Self, size: usize) -> Self {
+                    $ty::wrapping_sub(self, count.wrapping_mul(size as $ty))
+                }
+                fn wrapping_offset_from(self, origin: Self, size: usize) -> $signed {
+                    // Both offsets are in the pool, so their distance in bytes fits in an isize
+                    // but not necessarily in the signed offset type
+                    ((self as isize - origin as isize) / size as isize) as $signed
+                }
+                fn align_offset(self, align: Self, size: usize) -> Self {
+                    assert!(align.is_power_of_two(), "align must be a power of two");
+                    let bytes = ($ty::wrapping_add(self, align).wrapping_sub(1)
+                        & !align.wrapping_sub(1))
+                        .wrapping_sub(self);
+                    (bytes as usize / size) as $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_offset! {
+    u8, i8, NonZeroU8;
+    u16, i16, NonZeroU16;
+    u32, i32, NonZeroU32;
+}
+
+/// Reinterprets an offset as its non-zero or optional non-zero version, or the other way around
+///
+/// # Safety
+/// `A` and `B` have to be an offset, its `NonZero` or `Option<NonZero>`, and a `NonZero` must
+/// not be created from a null offset.
+pub(crate) const unsafe fn cast<A: Copy, B: Copy>(value: A) -> B {
+    union Cast<A: Copy, B: Copy> {
+        a: A,
+        b: B,
+    }
+    Cast { a: value }.b
+}
@@ -0,0 +1,548 @@
+//! Scaled pointer
+
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use crate::{base_ptr_mut, Pointable, PointerConversionError, Ref, RefMut};
+
+use super::{offset::cast, MutPtr, Offset};
+
+/// Error returned when a pointer cannot be converted to a [`ScaledPtr`]
+#[derive(Debug, Clone)]
+pub enum ScaledPtrError<T: ?Sized + Pointable> {
+    /// The pointer is not aligned to [`ScaledPtr::ALIGN`]
+    Misaligned,
+    /// The pointer does not fit into the address space or its metadata cannot be reduced
+    Conversion(PointerConversionError<T>),
+}
+
+impl<T: ?Sized + Pointable> From<PointerConversionError<T>> for ScaledPtrError<T> {
+    fn from(error: PointerConversionError<T>) -> Self {
+        Self::Conversion(error)
+    }
+}
+
+/// A tiny pointer that stores its offset shifted right by `SHIFT` bits
+///
+/// With all objects aligned to `2^SHIFT` bytes, a pool of `2^SHIFT` times the address space of
+/// `O` can be addressed, e.g. 256 kiB with `u16` offsets and a shift of 2. `BASE` has to be
+/// aligned to `2^SHIFT` bytes as well. Pointer arithmetic needs the size of `T` to be a multiple
+/// of `2^SHIFT`.
+pub struct ScaledPtr<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset = u16> {
+    ptr: O,
+    meta: <T as Pointable>::PointerMetaTiny,
+    _marker: PhantomData<*const T>,
+}
+
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset>
+    ScaledPtr<T, BASE, SHIFT, O>
+{
+    /// Alignment that every pointer needs, in bytes
+    pub const ALIGN: usize = 1 << SHIFT;
+    /// Evaluated when converting pointers, so a misaligned base fails to compile
+    const BASE_ALIGNED: () = assert!(BASE % Self::ALIGN == 0, "BASE is not aligned to the scale");
+
+    /// Create a new pointer from the scaled offset and the metadata
+    pub const fn from_raw_parts(ptr: O, meta: <T as Pointable>::PointerMetaTiny) -> Self {
+        Self {
+            ptr,
+            meta,
+            _marker: PhantomData,
+        }
+    }
+    /// Creates a scaled pointer unchecked
+    ///
+    /// # Safety
+    /// The pointer has to be aligned to [`ALIGN`](Self::ALIGN) and fit in the address space.
+    pub unsafe fn new_unchecked(ptr: *mut T) -> Self {
+        let () = Self::BASE_ALIGNED;
+        let (addr, meta) = T::extract_parts(ptr);
+        let offset = if ptr.is_null() {
+            0
+        } else {
+            addr.wrapping_sub(BASE)
+        };
+        Self::from_raw_parts(O::truncate(offset >> SHIFT), T::tiny_unchecked(meta))
+    }
+    /// Tries to create a scaled pointer from a pointer
+    ///
+    /// # Errors
+    /// Returns an error if the pointer is not aligned to [`ALIGN`](Self::ALIGN) or does not fit
+    /// in the address space
+    pub fn new(ptr: *mut T) -> Result<Self, ScaledPtrError<T>> {
+        let () = Self::BASE_ALIGNED;
+        let (addr, meta) = T::extract_parts(ptr);
+        if addr & (Self::ALIGN - 1) != 0 {
+            return Err(ScaledPtrError::Misaligned);
+        }
+        let offset = if ptr.is_null() {
+            0
+        } else {
+            addr.wrapping_sub(BASE)
+        };
+        let offset =
+            O::try_from(offset >> SHIFT).map_err(PointerConversionError::NotInAddressSpace)?;
+        let meta = T::try_tiny(meta).map_err(PointerConversionError::CannotReduceMeta)?;
+        Ok(Self::from_raw_parts(offset, meta))
+    }
+    /// Widens the pointer
+    pub fn wide(self) -> *mut T {
+        let addr = if self.is_null() {
+            0
+        } else {
+            (self.ptr.to_usize() << SHIFT).wrapping_add(BASE)
+        };
+        T::create_ptr_mut(base_ptr_mut::<BASE>(), addr, T::huge(self.meta))
+    }
+    /// Returns the pointer with its offset from `BASE` in bytes
+    ///
+    /// The offset has to fit in a `u32`, which it always does on 32 bit targets.
+    pub fn unscale(self) -> MutPtr<T, BASE, u32> {
+        MutPtr::from_raw_parts(u32::truncate(self.ptr.to_usize() << SHIFT), self.meta)
+    }
+    /// Returns `true` if the pointer is null
+    pub const fn is_null(self) -> bool {
+        // SAFETY: `Option<O::NonZero>` has the layout of `O`, with the null offset as `None`
+        unsafe { cast::<O, Option<O::NonZero>>(self.ptr) }.is_none()
+    }
+    /// Casts to a pointer of another type
+    pub const fn cast<U: Pointable<PointerMetaTiny = ()>>(self) -> ScaledPtr<U, BASE, SHIFT, O>
+    where
+        T: Pointable<PointerMetaTiny = ()>,
+    {
+        ScaledPtr::from_raw_parts(self.ptr, ())
+    }
+    /// Use the pointer value in a new pointer of another type
+    pub const fn with_metadata_of<U: Pointable + ?Sized>(
+        self,
+        val: ScaledPtr<U, BASE, SHIFT, O>,
+    ) -> ScaledPtr<U, BASE, SHIFT, O> {
+        ScaledPtr::from_raw_parts(self.ptr, val.meta)
+    }
+    /// Gets the scaled address portion of the pointer
+    pub const fn addr(self) -> O
+    where
+        T: Sized,
+    {
+        self.ptr
+    }
+    /// Creates a new pointer with the given scaled address
+    pub const fn with_addr(self, addr: O) -> Self
+    where
+        T: Sized,
+    {
+        Self::from_raw_parts(addr, self.meta)
+    }
+    /// Creates a new pointer by mapping self’s scaled address to a new one
+    pub fn map_addr(self, f: impl FnOnce(O) -> O) -> Self
+    where
+        T: Sized,
+    {
+        self.with_addr(f(self.addr()))
+    }
+    /// Decompose a pointer into its scaled address and metadata
+    pub const fn to_raw_parts(self) -> (O, <T as Pointable>::PointerMetaTiny) {
+        (self.ptr, self.meta)
+    }
+    /// Returns a tiny reference to the value, or `None` if the pointer is null
+    ///
+    /// The reference stores the unscaled offset, see [`unscale`](Self::unscale).
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to point to a valid value of `T` that is not mutated
+    /// for `'a`.
+    pub unsafe fn as_ref<'a>(self) -> Option<Ref<'a, T, BASE, u32>> {
+        self.unscale().as_ref()
+    }
+    /// Returns a mutable tiny reference to the value, or `None` if the pointer is null
+    ///
+    /// The reference stores the unscaled offset, see [`unscale`](Self::unscale).
+    ///
+    /// # Safety
+    /// If the pointer is not null, it has to point to a valid value of `T` that is not accessed
+    /// through any other pointer for `'a`.
+    pub unsafe fn as_mut<'a>(self) -> Option<RefMut<'a, T, BASE, u32>> {
+        self.unscale().as_mut()
+    }
+    /// Number of scaled address units per `T`
+    ///
+    /// # Panics
+    /// This function panics if the size of `T` is not a multiple of [`ALIGN`](Self::ALIGN).
+    const fn stride() -> usize
+    where
+        T: Sized,
+    {
+        let size = core::mem::size_of::<T>();
+        assert!(
+            size % Self::ALIGN == 0,
+            "size is not a multiple of the scale"
+        );
+        size >> SHIFT
+    }
+    /// Reads the value from self without moving it. this leaves the memory in self unchanged.
+    ///
+    /// # Safety
+    /// The pointer has to be valid for reads and point to an initialized `T`.
+    pub unsafe fn read(self) -> T
+    where
+        T: Sized,
+    {
+        self.wide().read()
+    }
+    /// Performs a volatile read of the value from self without moving it. this leaves the memory
+    /// in self unchanged.
+    ///
+    /// # Safety
+    /// The pointer has to be valid for reads and point to an initialized `T`.
+    pub unsafe fn read_volatile(self) -> T
+    where
+        T: Sized,
+    {
+        self.wide().read_volatile()
+    }
+    /// Overwrites a memory location with the given value without reading or dropping the old value
+    ///
+    /// # Safety
+    /// The pointer has to be valid for writes.
+    pub unsafe fn write(self, val: T)
+    where
+        T: Sized,
+    {
+        self.wide().write(val)
+    }
+    /// Performs a volatile write of a memory location
+    ///
+    /// # Safety
+    /// The pointer has to be valid for writes.
+    pub unsafe fn write_volatile(self, val: T)
+    where
+        T: Sized,
+    {
+        self.wide().write_volatile(val)
+    }
+    /// Invokes a memset on the specified pointer, setting count * size_of::<T>() bytes of memory
+    /// starting at self to val
+    ///
+    /// # Safety
+    /// The memory has to be valid for writes.
+    pub unsafe fn write_bytes(self, val: u8, count: O)
+    where
+        T: Sized,
+    {
+        self.wide().write_bytes(val, count.to_usize())
+    }
+    /// Replace the value of self with source, returning the old value
+    ///
+    /// # Safety
+    /// The pointer has to be valid for reads and writes and point to an initialized `T`.
+    pub unsafe fn replace(self, src: T) -> T
+    where
+        T: Sized,
+    {
+        self.wide().replace(src)
+    }
+    /// Swaps the values at two mutable locations
+    ///
+    /// # Safety
+    /// Both pointers have to be valid for reads and writes and point to initialized values.
+    pub unsafe fn swap(self, with: Self)
+    where
+        T: Sized,
+    {
+        self.wide().swap(with.wide())
+    }
+    /// Copies count * size_of<T> bytes from src to self. the source and destination may overlap
+    ///
+    /// # Safety
+    /// Both ranges have to be valid.
+    pub unsafe fn copy_from(self, src: Self, count: O)
+    where
+        T: Sized,
+    {
+        self.wide().copy_from(src.wide(), count.to_usize())
+    }
+    /// Copies count * size_of<T> bytes from src to self. The source and destination may *not*
+    /// overlap
+    ///
+    /// # Safety
+    /// Both ranges have to be valid and must not overlap.
+    pub unsafe fn copy_from_nonoverlapping(self, src: Self, count: O)
+    where
+        T: Sized,
+    {
+        self.wide()
+            .copy_from_nonoverlapping(src.wide(), count.to_usize())
+    }
+    /// Executes any destructor of the pointed-to value
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T` that is not used afterwards.
+    pub unsafe fn drop_in_place(self) {
+        self.wide().drop_in_place()
+    }
+}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const SHIFT: u8, O: Offset>
+    ScaledPtr<[T], BASE, SHIFT, O>
+{
+    /// Returns `true` if the slice has no elements
+    pub const fn is_empty(self) -> bool {
+        self.meta == 0
+    }
+    /// Returns a pointer to the first element of the slice
+    pub const fn as_mut_ptr(self) -> ScaledPtr<T, BASE, SHIFT, O> {
+        ScaledPtr::from_raw_parts(self.ptr, ())
+    }
+}
+
+macro_rules! impl_arithmetic {
+    ($($ty:ident, $signed:ident);* $(;)?) => {
+        $(
+            impl<T: Pointable, const BASE: usize, const SHIFT: u8> ScaledPtr<T, BASE, SHIFT, $ty> {
+                /// Calculates the offset from a pointer
+                ///
+                /// # Safety
+                /// The resulting pointer has to be in bounds of the same allocated object.
+                pub const unsafe fn offset(self, count: $signed) -> Self {
+                    self.wrapping_offset(count)
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_offset(mut self, count: $signed) -> Self {
+                    self.ptr = self
+                        .ptr
+                        .wrapping_add_signed(count.wrapping_mul(Self::stride() as $signed));
+                    self
+                }
+                /// Calculates the distance from `origin` to the pointer
+                ///
+                /// # Safety
+                /// Both pointers have to be in bounds of the same allocated object.
+                pub const unsafe fn offset_from(self, origin: Self) -> $signed {
+                    self.wrapping_offset_from(origin)
+                }
+                /// Calculates the distance from `origin` to the pointer using wrapping arithmetic
+                ///
+                /// # Panics
+                /// This function panics if `T` is zero-sized.
+                pub const fn wrapping_offset_from(self, origin: Self) -> $signed {
+                    ((self.ptr as isize - origin.ptr as isize) / Self::stride() as isize) as $signed
+                }
+                /// Calculates the distance between two pointers where it is known that self is equal
+                /// or greater than origin
+                ///
+                /// # Safety
+                /// Both pointers have to be in bounds of the same allocated object.
+                pub const unsafe fn sub_ptr(self, origin: Self) -> $ty {
+                    (self.ptr.wrapping_sub(origin.ptr) as usize / Self::stride()) as $ty
+                }
+                /// Calculates the offset from a pointer
+                ///
+                /// # Safety
+                /// The resulting pointer has to be in bounds of the same allocated object.
+                pub const unsafe fn add(self, count: $ty) -> Self {
+                    self.wrapping_add(count)
+                }
+                /// Calculates the offset from a pointer
+                ///
+                /// # Safety
+                /// The resulting pointer has to be in bounds of the same allocated object.
+                pub const unsafe fn sub(self, count: $ty) -> Self {
+                    self.wrapping_sub(count)
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_add(mut self, count: $ty) -> Self {
+                    self.ptr = self.ptr.wrapping_add(count.wrapping_mul(Self::stride() as $ty));
+                    self
+                }
+                /// Calculates the offset from a pointer using wrapping arithmetic
+                pub const fn wrapping_sub(mut self, count: $ty) -> Self {
+                    self.ptr = self.ptr.wrapping_sub(count.wrapping_mul(Self::stride() as $ty));
+                    self
+                }
+            }
+
+            impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const SHIFT: u8>
+                ScaledPtr<[T], BASE, SHIFT, $ty>
+            {
+                /// Returns the number of elements of the slice
+                ///
+                /// The length is stored as a `u16`, see [`ConstPtr::len`](super::ConstPtr::len).
+                #[allow(clippy::unnecessary_cast)]
+                pub const fn len(self) -> $ty {
+                    self.meta as $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_arithmetic! {
+    u8, i8;
+    u16, i16;
+    u32, i32;
+}
+
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> From<MutPtr<T, BASE, O>>
+    for ScaledPtr<T, BASE, 0, O>
+{
+    fn from(ptr: MutPtr<T, BASE, O>) -> Self {
+        Self::from_raw_parts(ptr.ptr, ptr.meta)
+    }
+}
+
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> From<ScaledPtr<T, BASE, 0, O>>
+    for MutPtr<T, BASE, O>
+{
+    fn from(ptr: ScaledPtr<T, BASE, 0, O>) -> Self {
+        Self::from_raw_parts(ptr.ptr, ptr.meta)
+    }
+}
+
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> Clone
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> Copy
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> PartialEq
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+    fn eq(&self, other: &Self) -> bool {
+        (self.ptr == other.ptr) && (self.meta == other.meta)
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> Eq
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> Ord
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ptr.cmp(&other.ptr)
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> PartialOrd
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> Hash
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(BASE);
+        self.ptr.hash(state);
+        self.meta.hash(state);
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> fmt::Debug
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(self, f)
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, const SHIFT: u8, O: Offset> fmt::Pointer
+    for ScaledPtr<T, BASE, SHIFT, O>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.wide(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: usize = 0x4_0000;
+
+    fn at<T>(offset: usize) -> *mut T {
+        (BASE + offset) as *mut T
+    }
+
+    #[test]
+    fn converts_scaled_offsets() {
+        let ptr = ScaledPtr::<u32, BASE, 2>::new(at(0x3_0000)).unwrap();
+        assert_eq!(ptr.addr(), 0xC000);
+        assert_eq!(ptr.wide(), at(0x3_0000));
+        assert_eq!(ptr.unscale().addr(), 0x3_0000);
+        assert!(matches!(
+            ScaledPtr::<u8, BASE, 2>::new(at(1)),
+            Err(ScaledPtrError::Misaligned)
+        ));
+        assert!(matches!(
+            ScaledPtr::<u32, BASE, 2>::new(at(0x4_0000)),
+            Err(ScaledPtrError::Conversion(_))
+        ));
+    }
+
+    #[test]
+    fn uses_offset_width() {
+        assert_eq!(
+            ScaledPtr::<u64, BASE, 3, u8>::new(at(0x7F8))
+                .unwrap()
+                .addr(),
+            0xFF
+        );
+        assert!(ScaledPtr::<u64, BASE, 3, u8>::new(at(0x800)).is_err());
+        assert_eq!(
+            ScaledPtr::<u32, BASE, 2, u32>::new(at(0x10_0000))
+                .unwrap()
+                .addr(),
+            0x4_0000
+        );
+    }
+
+    #[test]
+    fn null_pointer() {
+        let ptr = ScaledPtr::<u32, BASE, 2>::new(core::ptr::null_mut()).unwrap();
+        assert!(ptr.is_null());
+        assert!(ptr.wide().is_null());
+        assert!(unsafe { ptr.as_ref() }.is_none());
+        assert!(unsafe { ptr.as_mut() }.is_none());
+    }
+
+    #[test]
+    fn measures_distance_from_origin() {
+        let ptr = ScaledPtr::<[u32; 4], BASE, 2>::from_raw_parts(0x100, ());
+        assert_eq!(ptr.wrapping_add(3).addr(), 0x10C);
+        assert_eq!(ptr.wrapping_offset(-1).addr(), 0xFC);
+        assert_eq!(ptr.wrapping_add(3).wrapping_offset_from(ptr), 3);
+        assert_eq!(ptr.wrapping_offset_from(ptr.wrapping_add(3)), -3);
+        assert_eq!(unsafe { ptr.wrapping_add(3).sub_ptr(ptr) }, 3);
+        // Two elements are further apart than `i8::MAX` scaled units
+        let ptr = ScaledPtr::<[u32; 64], BASE, 2, u8>::from_raw_parts(0, ());
+        assert_eq!(ptr.wrapping_add(2).addr(), 128);
+        assert_eq!(ptr.wrapping_add(2).wrapping_offset_from(ptr), 2);
+        assert_eq!(ptr.wrapping_offset_from(ptr.wrapping_add(2)), -2);
+    }
+
+    #[test]
+    fn const_arithmetic() {
+        const PTR: ScaledPtr<u32, BASE, 2> =
+            ScaledPtr::<u32, BASE, 2>::from_raw_parts(4, ()).wrapping_add(2);
+        const NULL: bool = ScaledPtr::<u32, BASE, 2>::from_raw_parts(0, ()).is_null();
+        assert_eq!(PTR.addr(), 6);
+        assert!(NULL);
+    }
+
+    #[test]
+    fn converts_unscaled_pointers() {
+        let ptr = MutPtr::<u8, BASE>::from_raw_parts(0x123, ());
+        let scaled = ScaledPtr::<u8, BASE, 0>::from(ptr);
+        assert_eq!(scaled.addr(), 0x123);
+        assert_eq!(MutPtr::from(scaled), ptr);
+    }
+}
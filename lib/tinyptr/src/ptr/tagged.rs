@@ -0,0 +1,127 @@
+//! Tagged pointer
+
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use crate::Pointable;
+
+use super::MutPtr;
+
+/// A tiny pointer with a `BITS` bit tag in its unused low alignment bits
+///
+/// The alignment of `T` has to be at least `2^BITS`.
+pub struct TaggedPtr<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8> {
+    raw: u16,
+    _marker: PhantomData<MutPtr<T, BASE>>,
+}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8>
+    TaggedPtr<T, BASE, BITS>
+{
+    /// Mask of the tag bits
+    pub const TAG_MASK: u16 = (1 << BITS) - 1;
+    /// Evaluated when creating pointers, so a misaligned base fails to compile
+    const BASE_ALIGNED: () = assert!(BASE % (1 << BITS) == 0, "BASE is not aligned to the tag");
+    /// Evaluated when creating pointers, so a type without room for the tag fails to compile
+    const TYPE_ALIGNED: () = assert!(
+        core::mem::align_of::<T>() >= 1 << BITS,
+        "alignment too small for tag"
+    );
+
+    /// Creates a tagged pointer
+    ///
+    /// # Panics
+    /// This function panics if the pointer is not aligned or the tag does not fit into `BITS`
+    /// bits.
+    pub fn new(ptr: MutPtr<T, BASE>, tag: u16) -> Self {
+        let () = Self::BASE_ALIGNED;
+        let () = Self::TYPE_ALIGNED;
+        assert!(ptr.ptr & Self::TAG_MASK == 0, "pointer is not aligned");
+        assert!(tag <= Self::TAG_MASK, "tag does not fit");
+        Self {
+            raw: ptr.ptr | tag,
+            _marker: PhantomData,
+        }
+    }
+    /// Creates a tagged pointer from its raw representation
+    pub const fn from_raw(raw: u16) -> Self {
+        let () = Self::BASE_ALIGNED;
+        let () = Self::TYPE_ALIGNED;
+        Self {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns the raw representation, e.g. for storing it in an atomic
+    pub const fn into_raw(self) -> u16 {
+        self.raw
+    }
+    /// Returns the pointer without the tag
+    pub const fn ptr(self) -> MutPtr<T, BASE> {
+        MutPtr::from_raw_parts(self.raw & !Self::TAG_MASK, ())
+    }
+    /// Returns the tag
+    pub const fn tag(self) -> u16 {
+        self.raw & Self::TAG_MASK
+    }
+    /// Sets the tag
+    ///
+    /// # Panics
+    /// This function panics if the tag does not fit into `BITS` bits.
+    pub fn set_tag(&mut self, tag: u16) {
+        assert!(tag <= Self::TAG_MASK, "tag does not fit");
+        self.raw = (self.raw & !Self::TAG_MASK) | tag;
+    }
+    /// Returns the pointer with another tag
+    ///
+    /// # Panics
+    /// This function panics if the tag does not fit into `BITS` bits.
+    pub fn with_tag(mut self, tag: u16) -> Self {
+        self.set_tag(tag);
+        self
+    }
+    /// Widens the pointer, without the tag
+    pub fn wide(self) -> *mut T {
+        self.ptr().wide()
+    }
+}
+
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8> Clone
+    for TaggedPtr<T, BASE, BITS>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8> Copy
+    for TaggedPtr<T, BASE, BITS>
+{
+}
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8> PartialEq
+    for TaggedPtr<T, BASE, BITS>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8> Eq
+    for TaggedPtr<T, BASE, BITS>
+{
+}
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8> Hash
+    for TaggedPtr<T, BASE, BITS>
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+impl<T: Pointable<PointerMetaTiny = ()>, const BASE: usize, const BITS: u8> fmt::Debug
+    for TaggedPtr<T, BASE, BITS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}#{}", self.ptr(), self.tag())
+    }
+}
@@ -1,69 +1,117 @@
-use core::{marker::{PhantomData, Unsize}, ops::CoerceUnsized, fmt};
+use core::{
+    fmt,
+    marker::{PhantomData, Unsize},
+    ops::CoerceUnsized,
+};
 
-use crate::Pointable;
+use crate::{Pointable, Ref, RefMut};
 
-use super::{NonNull, MutPtr};
+use super::{MutPtr, NonNull, Offset};
 
 /// Unique pointer
 #[repr(transparent)]
-pub struct Unique<T: Pointable + ?Sized, const BASE: usize> {
-    pub(crate) pointer: NonNull<T, BASE>,
-    _marker: PhantomData<T>
+pub struct Unique<T: Pointable + ?Sized, const BASE: usize, O: Offset = u16> {
+    pub(crate) pointer: NonNull<T, BASE, O>,
+    _marker: PhantomData<T>,
 }
 
-unsafe impl<T: Pointable + Send + ?Sized, const BASE: usize> Send for Unique<T, BASE> {}
-unsafe impl<T: Pointable + Sync + ?Sized, const BASE: usize> Sync for Unique<T, BASE> {}
+unsafe impl<T: Pointable + Send + ?Sized, const BASE: usize, O: Offset> Send
+    for Unique<T, BASE, O>
+{
+}
+unsafe impl<T: Pointable + Sync + ?Sized, const BASE: usize, O: Offset> Sync
+    for Unique<T, BASE, O>
+{
+}
 
-impl<T: Pointable<PointerMetaTiny = ()> + Sized, const BASE: usize> Unique<T, BASE> {
-    pub const fn dangling() -> Self {
-        Self::from(NonNull::dangling())
-    }
+macro_rules! impl_dangling {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: Pointable<PointerMetaTiny = ()> + Sized, const BASE: usize> Unique<T, BASE, $ty> {
+                pub const fn dangling() -> Self {
+                    Self::from(NonNull::<T, BASE, $ty>::dangling())
+                }
+            }
+        )*
+    };
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Unique<T, BASE> {
-    pub const unsafe fn new_unchecked(ptr: MutPtr<T, BASE>) -> Self {
+impl_dangling!(u8, u16, u32);
+
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Unique<T, BASE, O> {
+    pub const unsafe fn new_unchecked(ptr: MutPtr<T, BASE, O>) -> Self {
         Self::from(NonNull::new_unchecked(ptr))
     }
-    pub const fn new(ptr: MutPtr<T, BASE>) -> Option<Self> {
+    pub const fn new(ptr: MutPtr<T, BASE, O>) -> Option<Self> {
         match NonNull::new(ptr) {
-            Some(v) => Some(Self::from(v)),
-            None => None
+            Some(pointer) => Some(Self::from(pointer)),
+            None => None,
         }
     }
-    pub const fn as_ptr(self) -> MutPtr<T, BASE> {
+    pub const fn as_ptr(self) -> MutPtr<T, BASE, O> {
         self.pointer.as_ptr()
     }
-    // TODO: as_ref
-    // TODO: as_mut
-    pub const fn cast<U>(self) -> Unique<U, BASE>
-    where U: Pointable<PointerMetaTiny = ()> + Sized
+    /// Returns a tiny reference to the value
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T`.
+    pub unsafe fn as_ref(&self) -> Ref<'_, T, BASE, O> {
+        self.pointer.as_ref()
+    }
+    /// Returns a mutable tiny reference to the value
+    ///
+    /// # Safety
+    /// The pointer has to point to a valid value of `T`.
+    pub unsafe fn as_mut(&mut self) -> RefMut<'_, T, BASE, O> {
+        self.pointer.as_mut()
+    }
+    pub const fn cast<U>(self) -> Unique<U, BASE, O>
+    where
+        U: Pointable<PointerMetaTiny = ()> + Sized,
     {
         Unique::from(self.pointer.cast())
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Clone for Unique<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Clone for Unique<T, BASE, O> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Copy for Unique<T, BASE> {}
-impl<T: Pointable + ?Sized, U: Pointable + ?Sized, const BASE: usize> CoerceUnsized<Unique<U, BASE>> for Unique<T, BASE> where T: Unsize<U>, <T as Pointable>::PointerMetaTiny: CoerceUnsized<<U as Pointable>::PointerMetaTiny> {}
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Debug for Unique<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Copy for Unique<T, BASE, O> {}
+impl<T: Pointable + ?Sized, U: Pointable + ?Sized, const BASE: usize, O: Offset>
+    CoerceUnsized<Unique<U, BASE, O>> for Unique<T, BASE, O>
+where
+    T: Unsize<U>,
+    <T as Pointable>::PointerMetaTiny: CoerceUnsized<<U as Pointable>::PointerMetaTiny>,
+{
+}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Debug for Unique<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.as_ptr(), f)
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> fmt::Pointer for Unique<T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> fmt::Pointer for Unique<T, BASE, O> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.as_ptr(), f)
     }
 }
 
-// TODO: From<RefMut<T>>
-impl<T: Pointable + ?Sized, const BASE: usize> const From<NonNull<T, BASE>> for Unique<T, BASE> {
-    fn from(pointer: NonNull<T, BASE>) -> Self {
-        Unique { pointer, _marker: PhantomData }
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> From<RefMut<'_, T, BASE, O>>
+    for Unique<T, BASE, O>
+{
+    fn from(reference: RefMut<'_, T, BASE, O>) -> Self {
+        Self::from(reference.ptr)
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> const From<NonNull<T, BASE, O>>
+    for Unique<T, BASE, O>
+{
+    fn from(pointer: NonNull<T, BASE, O>) -> Self {
+        Unique {
+            pointer,
+            _marker: PhantomData,
+        }
     }
 }
@@ -1,21 +1,34 @@
 use core::{marker::PhantomData, ops::Deref, borrow::Borrow};
 
-use crate::{Pointable, ptr::NonNull};
+use crate::{Pointable, ptr::{ConstPtr, NonNull, Offset}};
 
 /// Constant Tiny Reference
 #[repr(transparent)]
-pub struct Ref<'a, T: Pointable + ?Sized, const BASE: usize> {
-    pub(crate) ptr: NonNull<T, BASE>,
+pub struct Ref<'a, T: Pointable + ?Sized, const BASE: usize, O: Offset = u16> {
+    pub(crate) ptr: NonNull<T, BASE, O>,
     pub(crate) _marker: PhantomData<&'a T>
 }
 
-impl<T: Pointable + ?Sized, const BASE: usize> Copy for Ref<'_, T, BASE> {}
-impl<T: Pointable + ?Sized, const BASE: usize> Clone for Ref<'_, T, BASE> {
+impl<'a, T: Pointable + ?Sized, const BASE: usize, O: Offset> Ref<'a, T, BASE, O> {
+    /// Tries to create a tiny reference from a reference
+    ///
+    /// Returns `None` if the reference does not fit in the address space
+    pub fn new(reference: &'a T) -> Option<Self> {
+        let ptr = ConstPtr::new(reference).ok()?;
+        Some(Self {
+            ptr: NonNull::new(ptr.as_mut())?,
+            _marker: PhantomData
+        })
+    }
+}
+
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Copy for Ref<'_, T, BASE, O> {}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Clone for Ref<'_, T, BASE, O> {
     fn clone(&self) -> Self {
         *self
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> Deref for Ref<'_, T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Deref for Ref<'_, T, BASE, O> {
     type Target = T;
     fn deref(&self) -> &T {
         // SAFETY: Reference must be valid to be constructed
@@ -24,7 +37,7 @@ impl<T: Pointable + ?Sized, const BASE: usize> Deref for Ref<'_, T, BASE> {
         }
     }
 }
-impl<T: Pointable + ?Sized, const BASE: usize> Borrow<T> for Ref<'_, T, BASE> {
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Borrow<T> for Ref<'_, T, BASE, O> {
     fn borrow(&self) -> &T {
         &*self
     }
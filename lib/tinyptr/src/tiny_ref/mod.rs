@@ -1,2 +1,6 @@
 mod const_ref;
 pub use const_ref::*;
+mod mut_ref;
+pub use mut_ref::*;
+mod slice;
+pub use slice::*;
@@ -0,0 +1,79 @@
+use core::{marker::PhantomData, ops::{Deref, DerefMut}, borrow::{Borrow, BorrowMut}};
+
+use crate::{Pointable, Ref, ptr::{MutPtr, NonNull, Offset}};
+
+/// Mutable Tiny Reference
+#[repr(transparent)]
+pub struct RefMut<'a, T: Pointable + ?Sized, const BASE: usize, O: Offset = u16> {
+    pub(crate) ptr: NonNull<T, BASE, O>,
+    pub(crate) _marker: PhantomData<&'a mut T>
+}
+
+impl<'a, T: Pointable + ?Sized, const BASE: usize, O: Offset> RefMut<'a, T, BASE, O> {
+    /// Tries to create a tiny reference from a reference
+    ///
+    /// Returns `None` if the reference does not fit in the address space
+    pub fn new(reference: &'a mut T) -> Option<Self> {
+        let ptr = MutPtr::new(reference).ok()?;
+        Some(Self {
+            ptr: NonNull::new(ptr)?,
+            _marker: PhantomData
+        })
+    }
+    /// Reborrows the reference for a shorter lifetime
+    pub fn reborrow(&mut self) -> RefMut<'_, T, BASE, O> {
+        RefMut {
+            ptr: self.ptr,
+            _marker: PhantomData
+        }
+    }
+    /// Returns the pointer to the value
+    pub fn as_ptr(&self) -> MutPtr<T, BASE, O> {
+        self.ptr.as_ptr()
+    }
+    /// Converts into a shared tiny reference
+    pub fn into_ref(self) -> Ref<'a, T, BASE, O> {
+        Ref {
+            ptr: self.ptr,
+            _marker: PhantomData
+        }
+    }
+}
+
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Deref for RefMut<'_, T, BASE, O> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: Reference must be valid to be constructed
+        unsafe {
+            &*self.ptr.as_ptr().wide()
+        }
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> DerefMut for RefMut<'_, T, BASE, O> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: Reference must be valid and unique to be constructed
+        unsafe {
+            &mut *self.ptr.as_ptr().wide()
+        }
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> Borrow<T> for RefMut<'_, T, BASE, O> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> BorrowMut<T> for RefMut<'_, T, BASE, O> {
+    fn borrow_mut(&mut self) -> &mut T {
+        self
+    }
+}
+impl<'a, T: Pointable + ?Sized, const BASE: usize, O: Offset> From<RefMut<'a, T, BASE, O>> for Ref<'a, T, BASE, O> {
+    fn from(reference: RefMut<'a, T, BASE, O>) -> Self {
+        reference.into_ref()
+    }
+}
+impl<T: Pointable + ?Sized, const BASE: usize, O: Offset> From<RefMut<'_, T, BASE, O>> for MutPtr<T, BASE, O> {
+    fn from(reference: RefMut<'_, T, BASE, O>) -> Self {
+        reference.as_ptr()
+    }
+}
@@ -0,0 +1,296 @@
+use core::{fmt, marker::PhantomData, ops::{Index, IndexMut}, slice};
+
+use crate::{Pool, Ref, RefMut, ptr::{ConstPtr, MutPtr, NonNull}};
+
+/// Error returned when a tiny slice pointer cannot be turned into a slice
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SliceError {
+    /// The pointer is null
+    Null,
+    /// The pointer is not aligned for the element type
+    Misaligned,
+    /// The slice does not fit into the pool
+    OutOfBounds
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceError::Null => f.write_str("slice pointer is null"),
+            SliceError::Misaligned => f.write_str("slice pointer is not aligned"),
+            SliceError::OutOfBounds => f.write_str("slice is outside of the pool")
+        }
+    }
+}
+
+/// Checks that a slice starting at `addr` with `len` elements lies inside the pool
+///
+/// Pools that are not registered are only checked against the 64 kiB address space.
+fn check_bounds<T, const BASE: usize>(
+    addr: u16,
+    len: u16
+) -> Result<NonNull<[T], BASE>, SliceError> {
+    let ptr = NonNull::new(MutPtr::from_raw_parts(addr, len)).ok_or(SliceError::Null)?;
+    if BASE.wrapping_add(usize::from(addr)) % core::mem::align_of::<T>() != 0 {
+        return Err(SliceError::Misaligned);
+    }
+    let end = usize::from(len)
+        .checked_mul(core::mem::size_of::<T>())
+        .and_then(|size| size.checked_add(usize::from(addr)))
+        .ok_or(SliceError::OutOfBounds)?;
+    if end > Pool::<BASE>::size().unwrap_or(0x1_0000) {
+        return Err(SliceError::OutOfBounds);
+    }
+    Ok(ptr)
+}
+
+/// Returns the pointer to the elements `start..start + len` of a slice
+///
+/// Slices are checked with [`check_bounds`] when they are created, so a subslice of one always
+/// fits into the address space.
+///
+/// # Panics
+/// This function panics if the subslice does not fit into the address space.
+fn subslice<T, const BASE: usize>(
+    ptr: NonNull<[T], BASE>,
+    start: u16,
+    len: u16
+) -> NonNull<[T], BASE> {
+    // An empty tail keeps the start pointer, as the address after the slice may be out of range
+    let start = if len == 0 { 0 } else { start };
+    let addr = usize::from(start)
+        .checked_mul(core::mem::size_of::<T>())
+        .and_then(|offset| offset.checked_add(usize::from(ptr.addr().get())))
+        .and_then(|addr| u16::try_from(addr).ok())
+        .expect("subslice is outside of the address space");
+    NonNull::new(MutPtr::from_raw_parts(addr, len)).expect("subslice is null")
+}
+
+/// Shared slice behind a tiny pointer
+pub struct TinySlice<'a, T, const BASE: usize> {
+    inner: Ref<'a, [T], BASE>
+}
+
+impl<'a, T, const BASE: usize> TinySlice<'a, T, BASE> {
+    /// Tries to create a tiny slice from a slice
+    ///
+    /// Returns `None` if the slice does not fit in the address space or the pool
+    pub fn new(slice: &'a [T]) -> Option<Self> {
+        let ptr = Ref::<[T], BASE>::new(slice)?.ptr;
+        Some(Self::from_non_null(check_bounds(ptr.addr().get(), ptr.len()).ok()?))
+    }
+    /// Creates a tiny slice from a tiny slice pointer, checking it against the pool bounds
+    ///
+    /// # Errors
+    /// This function returns an error if the pointer is null, not aligned, or the slice is not
+    /// inside the pool.
+    ///
+    /// # Safety
+    /// The elements have to be initialized and must not be mutated for `'a`.
+    pub unsafe fn from_ptr(ptr: ConstPtr<[T], BASE>) -> Result<Self, SliceError> {
+        Ok(Self::from_non_null(check_bounds(ptr.ptr, ptr.meta)?))
+    }
+    fn from_non_null(ptr: NonNull<[T], BASE>) -> Self {
+        Self {
+            inner: Ref {
+                ptr,
+                _marker: PhantomData
+            }
+        }
+    }
+    /// Returns the number of elements in the slice
+    pub fn len(&self) -> usize {
+        usize::from(self.inner.ptr.len())
+    }
+    /// Returns `true` if the slice has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Returns a reference to an element, or `None` if the index is out of bounds
+    pub fn get(&self, index: usize) -> Option<&'a T> {
+        self.as_slice().get(index)
+    }
+    /// Returns an iterator over the elements
+    pub fn iter(&self) -> slice::Iter<'a, T> {
+        self.as_slice().iter()
+    }
+    /// Divides the slice into two at `mid`
+    ///
+    /// # Panics
+    /// This function panics if `mid > len`.
+    pub fn split_at(&self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.len(), "mid > len");
+        let mid = mid as u16;
+        let len = self.inner.ptr.len();
+        (
+            Self::from_non_null(subslice(self.inner.ptr, 0, mid)),
+            Self::from_non_null(subslice(self.inner.ptr, mid, len - mid))
+        )
+    }
+    /// Returns the elements as a regular slice
+    pub fn as_slice(&self) -> &'a [T] {
+        // SAFETY: the slice is valid for 'a
+        unsafe { &*self.inner.ptr.as_ptr().wide() }
+    }
+    /// Returns the tiny pointer to the slice
+    pub fn as_ptr(&self) -> ConstPtr<[T], BASE> {
+        self.inner.ptr.as_ptr().as_const()
+    }
+}
+
+impl<T, const BASE: usize> Copy for TinySlice<'_, T, BASE> {}
+impl<T, const BASE: usize> Clone for TinySlice<'_, T, BASE> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T, const BASE: usize> Index<usize> for TinySlice<'_, T, BASE> {
+    type Output = T;
+    fn index(&self, index: usize) -> &T {
+        &self.as_slice()[index]
+    }
+}
+impl<'a, T, const BASE: usize> IntoIterator for TinySlice<'a, T, BASE> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<T: fmt::Debug, const BASE: usize> fmt::Debug for TinySlice<'_, T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+/// Mutable slice behind a tiny pointer
+pub struct TinySliceMut<'a, T, const BASE: usize> {
+    inner: RefMut<'a, [T], BASE>
+}
+
+impl<'a, T, const BASE: usize> TinySliceMut<'a, T, BASE> {
+    /// Tries to create a mutable tiny slice from a slice
+    ///
+    /// Returns `None` if the slice does not fit in the address space or the pool
+    pub fn new(slice: &'a mut [T]) -> Option<Self> {
+        let ptr = RefMut::<[T], BASE>::new(slice)?.ptr;
+        Some(Self::from_non_null(check_bounds(ptr.addr().get(), ptr.len()).ok()?))
+    }
+    /// Creates a mutable tiny slice from a tiny slice pointer, checking it against the pool
+    /// bounds
+    ///
+    /// # Errors
+    /// This function returns an error if the pointer is null, not aligned, or the slice is not
+    /// inside the pool.
+    ///
+    /// # Safety
+    /// The elements have to be initialized and must not be accessed through any other pointer
+    /// for `'a`.
+    pub unsafe fn from_ptr(ptr: MutPtr<[T], BASE>) -> Result<Self, SliceError> {
+        Ok(Self::from_non_null(check_bounds(ptr.ptr, ptr.meta)?))
+    }
+    fn from_non_null(ptr: NonNull<[T], BASE>) -> Self {
+        Self {
+            inner: RefMut {
+                ptr,
+                _marker: PhantomData
+            }
+        }
+    }
+    /// Returns the number of elements in the slice
+    pub fn len(&self) -> usize {
+        usize::from(self.inner.ptr.len())
+    }
+    /// Returns `true` if the slice has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Returns a reference to an element, or `None` if the index is out of bounds
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+    /// Returns a mutable reference to an element, or `None` if the index is out of bounds
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.as_mut_slice().get_mut(index)
+    }
+    /// Returns an iterator over the elements
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+    /// Returns an iterator that allows modifying each element
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+    /// Divides the slice into two at `mid`
+    ///
+    /// # Panics
+    /// This function panics if `mid > len`.
+    pub fn split_at(&self, mid: usize) -> (TinySlice<'_, T, BASE>, TinySlice<'_, T, BASE>) {
+        self.as_tiny_slice().split_at(mid)
+    }
+    /// Divides the slice into two mutable slices at `mid`
+    ///
+    /// # Panics
+    /// This function panics if `mid > len`.
+    pub fn split_at_mut(self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.len(), "mid > len");
+        let mid = mid as u16;
+        let len = self.inner.ptr.len();
+        (
+            Self::from_non_null(subslice(self.inner.ptr, 0, mid)),
+            Self::from_non_null(subslice(self.inner.ptr, mid, len - mid))
+        )
+    }
+    /// Copies all elements from `src` into the slice
+    ///
+    /// # Panics
+    /// This function panics if `src` does not have the same length as the slice.
+    pub fn copy_from_slice(&mut self, src: &[T])
+    where
+        T: Copy
+    {
+        self.as_mut_slice().copy_from_slice(src)
+    }
+    /// Reborrows the slice as a shared tiny slice
+    pub fn as_tiny_slice(&self) -> TinySlice<'_, T, BASE> {
+        TinySlice::from_non_null(self.inner.ptr)
+    }
+    /// Returns the elements as a regular slice
+    pub fn as_slice(&self) -> &[T] {
+        &self.inner
+    }
+    /// Returns the elements as a regular mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.inner
+    }
+    /// Converts into a shared tiny slice
+    pub fn into_tiny_slice(self) -> TinySlice<'a, T, BASE> {
+        TinySlice { inner: self.inner.into_ref() }
+    }
+    /// Returns the tiny pointer to the slice
+    pub fn as_ptr(&self) -> MutPtr<[T], BASE> {
+        self.inner.as_ptr()
+    }
+}
+
+impl<T, const BASE: usize> Index<usize> for TinySliceMut<'_, T, BASE> {
+    type Output = T;
+    fn index(&self, index: usize) -> &T {
+        &self.as_slice()[index]
+    }
+}
+impl<T, const BASE: usize> IndexMut<usize> for TinySliceMut<'_, T, BASE> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.as_mut_slice()[index]
+    }
+}
+impl<'a, T, const BASE: usize> From<TinySliceMut<'a, T, BASE>> for TinySlice<'a, T, BASE> {
+    fn from(slice: TinySliceMut<'a, T, BASE>) -> Self {
+        slice.into_tiny_slice()
+    }
+}
+impl<T: fmt::Debug, const BASE: usize> fmt::Debug for TinySliceMut<'_, T, BASE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
@@ -0,0 +1,242 @@
+//! Trait object support
+//!
+//! A trait object pointer carries a pointer to its vtable, which does not fit into a tiny
+//! pointer. Instead, the implementations that may be stored in a pool are registered with
+//! [`dyn_pointable!`](crate::dyn_pointable), and tiny pointers store the index of the
+//! implementation in that list.
+
+use core::{
+    any::{Any, TypeId},
+    fmt, hash,
+    marker::Unsize,
+    ptr::{self, DynMetadata, Pointee},
+};
+
+/// Vtable of a trait object
+pub struct VTable<T: ?Sized>(DynMetadata<T>);
+
+impl<T: ?Sized + Pointee<Metadata = DynMetadata<T>>> VTable<T> {
+    /// Returns the vtable of `U` as `T`
+    pub fn of<U: Unsize<T>>() -> Self {
+        Self(ptr::metadata(ptr::null::<U>() as *const T))
+    }
+    /// Returns the type behind the vtable
+    ///
+    /// The same vtable can be emitted more than once, so vtables are compared by the type they
+    /// belong to rather than by their address.
+    pub fn type_id(self) -> TypeId
+    where
+        T: TypeTag,
+    {
+        ptr::from_raw_parts::<T>(ptr::null::<()>(), self.0).type_tag()
+    }
+    /// Returns the size of the type behind the vtable
+    pub fn size_of(self) -> usize {
+        self.0.size_of()
+    }
+    /// Returns the address and vtable of a trait object pointer
+    pub fn extract_parts(ptr: *const T) -> (usize, Self) {
+        (ptr.addr(), Self(ptr::metadata(ptr)))
+    }
+    /// Returns a pointer to an address in a specific address space
+    pub fn create_ptr(self, base_ptr: *const (), address: usize) -> *const T {
+        ptr::from_raw_parts(base_ptr.with_addr(address), self.0)
+    }
+    /// Returns a mutable pointer to an address in a specific address space
+    pub fn create_ptr_mut(self, base_ptr: *mut (), address: usize) -> *mut T {
+        ptr::from_raw_parts_mut(base_ptr.with_addr(address), self.0)
+    }
+}
+
+impl<T: ?Sized> Copy for VTable<T> {}
+impl<T: ?Sized> Clone for VTable<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T: ?Sized> PartialEq for VTable<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<T: ?Sized> Eq for VTable<T> {}
+impl<T: ?Sized> hash::Hash for VTable<T> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+impl<T: ?Sized> fmt::Debug for VTable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Identifies the type behind a trait object
+///
+/// Traits used with [`dyn_pointable!`](crate::dyn_pointable) need this as a supertrait. It is
+/// implemented for every `'static` type.
+pub trait TypeTag {
+    /// Returns the [`TypeId`] of the implementing type
+    ///
+    /// Only the vtable of the pointer is used, so it may dangle or be null.
+    fn type_tag(self: *const Self) -> TypeId;
+}
+
+impl<T: Any> TypeTag for T {
+    fn type_tag(self: *const Self) -> TypeId {
+        TypeId::of::<T>()
+    }
+}
+
+/// The type behind a trait object was not registered with
+/// [`dyn_pointable!`](crate::dyn_pointable)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnregisteredType;
+
+impl fmt::Display for UnregisteredType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("type is not registered for this trait object")
+    }
+}
+
+/// Implements [`Pointable`](crate::Pointable) for a trait object
+///
+/// Lists the types that can be stored behind tiny pointers to the trait object, up to 256 of
+/// them. The tiny pointer metadata is the index of the type in the list, so a tiny pointer to a
+/// trait object takes 3 bytes, or 4 with padding. The trait needs [`TypeTag`] as a supertrait,
+/// which is used to find the type behind a vtable.
+///
+/// ```
+/// use tinyptr::{Pointable, TypeTag, VTable};
+///
+/// trait Effect: TypeTag {
+///     fn step(&mut self);
+/// }
+///
+/// struct Rainbow(u8);
+/// impl Effect for Rainbow {
+///     fn step(&mut self) {
+///         self.0 = self.0.wrapping_add(1);
+///     }
+/// }
+///
+/// struct Breathing;
+/// impl Effect for Breathing {
+///     fn step(&mut self) {}
+/// }
+///
+/// tinyptr::dyn_pointable!(Effect: Rainbow, Breathing);
+///
+/// assert_eq!(<dyn Effect>::try_tiny(VTable::of::<Breathing>()), Ok(1));
+/// assert!(<dyn Effect>::huge(0) == VTable::of::<Rainbow>());
+/// ```
+#[macro_export]
+macro_rules! dyn_pointable {
+    ($trait:path: $($ty:ty),+ $(,)?) => {
+        const _: () = assert!(
+            [$(stringify!($ty)),+].len() <= 256,
+            "at most 256 types can be registered for a trait object"
+        );
+
+        impl $crate::Pointable for dyn $trait {
+            type PointerMeta = $crate::VTable<dyn $trait>;
+            type PointerMetaTiny = u8;
+            type ConversionError = $crate::UnregisteredType;
+
+            fn try_tiny(meta: Self::PointerMeta) -> Result<u8, $crate::UnregisteredType> {
+                let type_id = meta.type_id();
+                [$(::core::any::TypeId::of::<$ty>()),+]
+                    .iter()
+                    .position(|&id| id == type_id)
+                    .map(|index| index as u8)
+                    .ok_or($crate::UnregisteredType)
+            }
+            fn huge(meta: u8) -> Self::PointerMeta {
+                let vtables: &[fn() -> $crate::VTable<dyn $trait>] =
+                    &[$($crate::VTable::of::<$ty>),+];
+                vtables.get(usize::from(meta)).expect("invalid vtable index")()
+            }
+            fn size_of_val(meta: &Self::PointerMeta) -> Option<usize> {
+                Some(meta.size_of())
+            }
+            fn extract_parts(ptr: *const Self) -> (usize, Self::PointerMeta) {
+                $crate::VTable::extract_parts(ptr)
+            }
+            fn create_ptr(
+                base_ptr: *const (),
+                address: usize,
+                meta: Self::PointerMeta,
+            ) -> *const Self {
+                meta.create_ptr(base_ptr, address)
+            }
+            fn create_ptr_mut(
+                base_ptr: *mut (),
+                address: usize,
+                meta: Self::PointerMeta,
+            ) -> *mut Self {
+                meta.create_ptr_mut(base_ptr, address)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pointable;
+
+    trait Shape: TypeTag {
+        fn area(&self) -> u32;
+    }
+
+    struct Square(u32);
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+    }
+
+    struct Rect(u16, u16);
+    impl Shape for Rect {
+        fn area(&self) -> u32 {
+            u32::from(self.0) * u32::from(self.1)
+        }
+    }
+
+    struct Point;
+    impl Shape for Point {
+        fn area(&self) -> u32 {
+            0
+        }
+    }
+
+    crate::dyn_pointable!(Shape: Square, Rect);
+
+    fn vtable(shape: &(dyn Shape + 'static)) -> VTable<dyn Shape> {
+        <dyn Shape>::extract_parts(shape).1
+    }
+
+    #[test]
+    fn finds_index_by_type() {
+        assert_eq!(<dyn Shape>::try_tiny(vtable(&Square(2))), Ok(0));
+        assert_eq!(<dyn Shape>::try_tiny(vtable(&Rect(2, 3))), Ok(1));
+        assert_eq!(<dyn Shape>::try_tiny(vtable(&Point)), Err(UnregisteredType));
+    }
+
+    #[test]
+    fn restores_vtable() {
+        let rect = Rect(2, 3);
+        let meta = <dyn Shape>::try_tiny(vtable(&rect)).unwrap();
+        let vtable = <dyn Shape>::huge(meta);
+        assert_eq!(vtable.type_id(), TypeId::of::<Rect>());
+        assert_eq!(<dyn Shape>::size_of_val(&vtable), Some(4));
+        let ptr = vtable.create_ptr(ptr::null(), (&rect as *const Rect).addr());
+        assert_eq!(unsafe { &*ptr }.area(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid vtable index")]
+    fn rejects_unknown_index() {
+        <dyn Shape>::huge(2);
+    }
+}
@@ -31,3 +31,7 @@ SECTIONS {
         __bi_entries_end = .;
     } > FLASH
 } INSERT AFTER .text;
+
+/* End of the firmware image in flash. `.data` is the last section loaded from flash; flash
+ * regions used for storage have to start after this. */
+PROVIDE(__flash_binary_end = LOADADDR(.data) + SIZEOF(.data));
@@ -0,0 +1,211 @@
+//! APA102 / SK9822 (DotStar) LED driver
+//!
+//! The LEDs are driven over a plain SPI bus (only SCK and MOSI are used), which makes them
+//! tolerant of slow level shifters compared to the single-wire WS2812.
+
+use embedded_hal::{blocking::spi::Write, spi};
+use embedded_time::rate::Hertz;
+
+use crate::color::{ColorCorrection, Rgb};
+
+/// SPI mode the LEDs expect
+///
+/// Data is latched on the rising edge of the clock.
+pub const MODE: spi::Mode = spi::MODE_3;
+
+/// Maximum value of the 5 bit global brightness field
+pub const MAX_GLOBAL_BRIGHTNESS: u8 = 0x1F;
+
+/// Chip variant of the LEDs on the chain
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Variant {
+    /// Original APA102/APA102C
+    Apa102,
+    /// SK9822 clone, which needs an additional latch frame
+    Sk9822,
+}
+
+impl Variant {
+    /// Highest clock rate the variant is specified for
+    pub const fn max_clock_rate(self) -> Hertz {
+        match self {
+            Variant::Apa102 => Hertz(20_000_000),
+            Variant::Sk9822 => Hertz(15_000_000),
+        }
+    }
+}
+
+/// Configuration of an LED chain
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Chip variant of the chain
+    pub variant: Variant,
+    /// Requested SPI clock rate
+    pub clock_rate: Hertz,
+    /// Initial global brightness (0-255)
+    pub brightness: u8,
+    /// Color correction applied to every LED
+    pub correction: ColorCorrection,
+}
+
+impl Config {
+    /// Default configuration for a chain of the given variant
+    pub const fn new(variant: Variant) -> Self {
+        Self {
+            variant,
+            clock_rate: Hertz(4_000_000),
+            brightness: u8::MAX,
+            correction: ColorCorrection::GAMMA,
+        }
+    }
+    /// Sets the requested SPI clock rate
+    pub const fn clock_rate(mut self, clock_rate: Hertz) -> Self {
+        self.clock_rate = clock_rate;
+        self
+    }
+    /// Sets the initial global brightness
+    pub const fn brightness(mut self, brightness: u8) -> Self {
+        self.brightness = brightness;
+        self
+    }
+    /// Sets the color correction
+    pub const fn correction(mut self, correction: ColorCorrection) -> Self {
+        self.correction = correction;
+        self
+    }
+    /// Clock rate to initialize the SPI peripheral with
+    ///
+    /// This is the requested clock rate clamped to what the variant supports. Long chains or
+    /// long wires may need lower rates than the maximum.
+    pub const fn spi_clock_rate(&self) -> Hertz {
+        let max = self.variant.max_clock_rate();
+        if self.clock_rate.0 > max.0 {
+            max
+        } else {
+            self.clock_rate
+        }
+    }
+}
+
+/// Driver for a chain of APA102 or SK9822 LEDs
+pub struct Apa102<SPI> {
+    spi: SPI,
+    variant: Variant,
+    global_brightness: u8,
+    correction: ColorCorrection,
+}
+
+impl<SPI: Write<u8>> Apa102<SPI> {
+    /// Creates a new driver
+    ///
+    /// The SPI peripheral has to be initialized with [`MODE`] and [`Config::spi_clock_rate`].
+    pub fn new(spi: SPI, config: &Config) -> Self {
+        let mut this = Self {
+            spi,
+            variant: config.variant,
+            global_brightness: MAX_GLOBAL_BRIGHTNESS,
+            correction: config.correction,
+        };
+        this.set_brightness(config.brightness);
+        this
+    }
+    /// Releases the SPI peripheral
+    pub fn free(self) -> SPI {
+        self.spi
+    }
+    /// Sets the global brightness (0-255)
+    ///
+    /// The brightness is applied using the 5 bit global brightness field of the LEDs rather than
+    /// by scaling the colors, so dimming the chain does not reduce the color resolution.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.global_brightness =
+            ((u16::from(brightness) * u16::from(MAX_GLOBAL_BRIGHTNESS) + 127) / 255) as u8;
+    }
+    /// Returns the global brightness (0-255)
+    pub fn brightness(&self) -> u8 {
+        ((u16::from(self.global_brightness) * 255 + 15) / u16::from(MAX_GLOBAL_BRIGHTNESS)) as u8
+    }
+    /// Sets the color correction
+    pub fn set_correction(&mut self, correction: ColorCorrection) {
+        self.correction = correction;
+    }
+    /// Writes the colors to the chain
+    ///
+    /// The colors are gamma corrected and white balanced before they are sent.
+    ///
+    /// # Errors
+    /// This function returns an error if the SPI transfer fails.
+    pub fn write(&mut self, colors: impl IntoIterator<Item = Rgb>) -> Result<(), SPI::Error> {
+        self.spi.write(&[0x00; 4])?;
+        let header = 0xE0 | self.global_brightness;
+        let mut count = 0usize;
+        for color in colors {
+            let color = self.correction.apply(color);
+            self.spi.write(&[header, color.b, color.g, color.r])?;
+            count += 1;
+        }
+        if self.variant == Variant::Sk9822 {
+            // The SK9822 only latches the new colors after another start frame
+            self.spi.write(&[0x00; 4])?;
+        }
+        // Every LED delays the data by half a clock cycle, so the data needs n/2 additional
+        // clock edges to propagate to the end of the chain.
+        for _ in 0..((count + 15) / 16) {
+            self.spi.write(&[0x00])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    /// SPI bus that records the written bytes
+    #[derive(Default)]
+    struct RecordingSpi(Vec<u8>);
+
+    impl Write<u8> for RecordingSpi {
+        type Error = Infallible;
+        fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+            self.0.extend_from_slice(words);
+            Ok(())
+        }
+    }
+
+    /// Writes `count` LEDs and returns the bytes sent after the last LED frame
+    fn trailer(variant: Variant, count: usize) -> Vec<u8> {
+        let config = Config::new(variant).correction(ColorCorrection::NONE);
+        let mut leds = Apa102::new(RecordingSpi::default(), &config);
+        leds.write((0..count).map(|_| Rgb::new(1, 2, 3))).unwrap();
+        let bytes = leds.free().0;
+        assert_eq!(bytes[..4], [0; 4]);
+        bytes[4 + 4 * count..].to_vec()
+    }
+
+    #[test]
+    fn end_frame_length() {
+        for (count, len) in [(0, 0), (1, 1), (16, 1), (17, 2), (32, 2), (33, 3)] {
+            assert_eq!(
+                trailer(Variant::Apa102, count),
+                vec![0; len],
+                "{count} LEDs"
+            );
+        }
+    }
+
+    #[test]
+    fn sk9822_latch_frame() {
+        assert_eq!(trailer(Variant::Sk9822, 0), [0; 4]);
+        assert_eq!(trailer(Variant::Sk9822, 17), [0; 6]);
+    }
+
+    #[test]
+    fn led_frame() {
+        let config = Config::new(Variant::Apa102).correction(ColorCorrection::NONE);
+        let mut leds = Apa102::new(RecordingSpi::default(), &config);
+        leds.write([Rgb::new(1, 2, 3)]).unwrap();
+        assert_eq!(leds.free().0[4..8], [0xFF, 3, 2, 1]);
+    }
+}
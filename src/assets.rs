@@ -0,0 +1,9 @@
+//! Images embedded into the firmware
+//!
+//! Generated by the build script, which converts every PPM image in `assets` to RGB565 and
+//! run-length encodes it into the format read by [`Image`]. `assets/logo.ppm` becomes
+//! [`LOGO`].
+
+use crate::st7789::Image;
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
@@ -0,0 +1,277 @@
+//! Fixed size bit sets
+//!
+//! Used for matrix state, layer masks and per-key flags. The size is given in bits and stored in
+//! 32 bit words; the unused bits of the last word are always clear.
+
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+/// Number of words needed to store `bits` bits
+pub const fn words(bits: usize) -> usize {
+    (bits + 31) / 32
+}
+
+/// Mask of the used bits in the last word of a set of `bits` bits
+const fn tail_mask(bits: usize) -> u32 {
+    match bits % 32 {
+        0 => u32::MAX,
+        used => (1 << used) - 1,
+    }
+}
+
+/// A set of `BITS` bits
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TinyBitSet<const BITS: usize>
+where
+    [(); words(BITS)]:,
+{
+    words: [u32; words(BITS)],
+}
+
+impl<const BITS: usize> TinyBitSet<BITS>
+where
+    [(); words(BITS)]:,
+{
+    /// Number of bits in the set
+    pub const BITS: usize = BITS;
+
+    /// Creates a set with all bits cleared
+    pub const fn new() -> Self {
+        Self {
+            words: [0; words(BITS)],
+        }
+    }
+    /// Creates a set from its words, with bit 0 being the lowest bit of the first word
+    ///
+    /// Bits past `BITS` in the last word are ignored.
+    pub const fn from_words(mut data: [u32; words(BITS)]) -> Self {
+        if BITS > 0 {
+            data[words(BITS) - 1] &= tail_mask(BITS);
+        }
+        Self { words: data }
+    }
+    /// Returns the words of the set
+    pub const fn as_words(&self) -> &[u32; words(BITS)] {
+        &self.words
+    }
+    /// Returns the value of a bit
+    ///
+    /// # Panics
+    /// This function panics if `bit` is out of range.
+    pub const fn get(&self, bit: usize) -> bool {
+        assert!(bit < BITS, "bit out of range");
+        self.words[bit / 32] & (1 << (bit % 32)) != 0
+    }
+    /// Sets a bit to a value
+    ///
+    /// # Panics
+    /// This function panics if `bit` is out of range.
+    pub fn set(&mut self, bit: usize, value: bool) {
+        if value {
+            self.insert(bit);
+        } else {
+            self.remove(bit);
+        }
+    }
+    /// Sets a bit
+    ///
+    /// # Panics
+    /// This function panics if `bit` is out of range.
+    pub fn insert(&mut self, bit: usize) {
+        assert!(bit < BITS, "bit out of range");
+        self.words[bit / 32] |= 1 << (bit % 32);
+    }
+    /// Clears a bit
+    ///
+    /// # Panics
+    /// This function panics if `bit` is out of range.
+    pub fn remove(&mut self, bit: usize) {
+        assert!(bit < BITS, "bit out of range");
+        self.words[bit / 32] &= !(1 << (bit % 32));
+    }
+    /// Inverts a bit
+    ///
+    /// # Panics
+    /// This function panics if `bit` is out of range.
+    pub fn toggle(&mut self, bit: usize) {
+        assert!(bit < BITS, "bit out of range");
+        self.words[bit / 32] ^= 1 << (bit % 32);
+    }
+    /// Clears all bits
+    pub fn clear(&mut self) {
+        self.words = [0; words(BITS)];
+    }
+    /// Returns `true` if no bit is set
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+    /// Returns the number of set bits
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+    /// Returns the bits that differ from `prev`
+    pub fn changed_bits(&self, prev: &Self) -> Self {
+        *self ^ *prev
+    }
+    /// Returns an iterator over the indices of the set bits, in ascending order
+    pub fn iter(&self) -> Ones<'_> {
+        Ones {
+            words: &self.words,
+            bits: BITS,
+            index: 0,
+            current: self.words.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+impl<const BITS: usize> Default for TinyBitSet<BITS>
+where
+    [(); words(BITS)]:,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const BITS: usize> IntoIterator for &'a TinyBitSet<BITS>
+where
+    [(); words(BITS)]:,
+{
+    type Item = usize;
+    type IntoIter = Ones<'a>;
+    fn into_iter(self) -> Ones<'a> {
+        self.iter()
+    }
+}
+
+macro_rules! word_op {
+    ($op:ident, $fn:ident, $assign_op:ident, $assign_fn:ident) => {
+        impl<const BITS: usize> $assign_op for TinyBitSet<BITS>
+        where
+            [(); words(BITS)]:,
+        {
+            fn $assign_fn(&mut self, rhs: Self) {
+                for (word, rhs) in self.words.iter_mut().zip(rhs.words) {
+                    $assign_op::$assign_fn(word, rhs);
+                }
+            }
+        }
+        impl<const BITS: usize> $op for TinyBitSet<BITS>
+        where
+            [(); words(BITS)]:,
+        {
+            type Output = Self;
+            fn $fn(mut self, rhs: Self) -> Self {
+                $assign_op::$assign_fn(&mut self, rhs);
+                self
+            }
+        }
+    };
+}
+
+word_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+word_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+word_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+impl<const BITS: usize> Not for TinyBitSet<BITS>
+where
+    [(); words(BITS)]:,
+{
+    type Output = Self;
+    fn not(mut self) -> Self {
+        for word in &mut self.words {
+            *word = !*word;
+        }
+        if let Some(last) = self.words.last_mut() {
+            *last &= tail_mask(BITS);
+        }
+        self
+    }
+}
+
+/// Iterator over the set bits of a [`TinyBitSet`]
+pub struct Ones<'a> {
+    words: &'a [u32],
+    bits: usize,
+    index: usize,
+    current: u32,
+}
+
+impl Iterator for Ones<'_> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.index += 1;
+            self.current = *self.words.get(self.index)?;
+        }
+        let bit = self.index * 32 + self.current.trailing_zeros() as usize;
+        if bit >= self.bits {
+            return None;
+        }
+        self.current &= self.current - 1;
+        Some(bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_count() {
+        assert_eq!(words(0), 0);
+        assert_eq!(words(1), 1);
+        assert_eq!(words(31), 1);
+        assert_eq!(words(32), 1);
+        assert_eq!(words(33), 2);
+        assert_eq!(words(64), 2);
+        assert_eq!(words(65), 3);
+    }
+
+    #[test]
+    fn empty_set() {
+        let set = TinyBitSet::<0>::new();
+        assert!(set.is_empty());
+        assert_eq!((!set).count(), 0);
+        assert_eq!(set.iter().next(), None);
+    }
+
+    #[test]
+    fn full_word() {
+        let set = !TinyBitSet::<32>::new();
+        assert_eq!(set.as_words(), &[u32::MAX]);
+        assert_eq!(set.count(), 32);
+        assert_eq!(set.iter().last(), Some(31));
+    }
+
+    #[test]
+    fn partial_last_word() {
+        let set = !TinyBitSet::<33>::new();
+        assert_eq!(set.as_words(), &[u32::MAX, 1]);
+        assert_eq!(set.count(), 33);
+        let set = TinyBitSet::<33>::from_words([0, u32::MAX]);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [32]);
+    }
+
+    #[test]
+    fn bits_across_words() {
+        let mut set = TinyBitSet::<65>::new();
+        for bit in [0, 31, 32, 63, 64] {
+            set.insert(bit);
+        }
+        assert_eq!(set.as_words(), &[0x8000_0001, 0x8000_0001, 1]);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [0, 31, 32, 63, 64]);
+        set.toggle(32);
+        set.remove(64);
+        assert!(!set.get(32) && !set.get(64) && set.get(63));
+        assert_eq!(set.count(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "bit out of range")]
+    fn out_of_range() {
+        TinyBitSet::<33>::new().insert(33);
+    }
+}
@@ -0,0 +1,89 @@
+//! Ownership handoff for shared buses
+//!
+//! A bus can be used from the scan loop, interrupt handlers and the other core. Instead of
+//! running whole transactions in a critical section, [`BusMutex`] only uses one to take the bus,
+//! so interrupts stay enabled while a transaction runs. A user that finds the bus taken gets
+//! [`Busy`] and tries again later instead of blocking.
+
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// The bus is used by someone else
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Busy;
+
+/// A bus peripheral that is handed to one user at a time
+pub struct BusMutex<T> {
+    taken: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: the value is only accessed through the guard, of which there is at most one
+unsafe impl<T: Send> Sync for BusMutex<T> {}
+
+impl<T> BusMutex<T> {
+    /// Creates a new mutex
+    pub const fn new(value: T) -> Self {
+        Self {
+            taken: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+    /// Takes the bus until the guard is dropped
+    ///
+    /// # Errors
+    /// This function returns [`Busy`] if the bus is already taken.
+    pub fn try_lock(&self) -> Result<BusGuard<'_, T>, Busy> {
+        // The Cortex-M0+ has no compare-and-swap, so the critical section makes the check and
+        // the store atomic, also against the other core.
+        let taken = critical_section::with(|_| {
+            let taken = self.taken.load(Ordering::Acquire);
+            if !taken {
+                self.taken.store(true, Ordering::Relaxed);
+            }
+            taken
+        });
+        if taken {
+            Err(Busy)
+        } else {
+            Ok(BusGuard { mutex: self })
+        }
+    }
+    /// Returns the peripheral, which is not shared while `self` is borrowed mutably
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+    /// Releases the peripheral
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+/// Exclusive access to a bus, which is released when the guard is dropped
+pub struct BusGuard<'a, T> {
+    mutex: &'a BusMutex<T>,
+}
+
+impl<T> Deref for BusGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: the guard is the only user of the bus
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T> DerefMut for BusGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the guard is the only user of the bus
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T> Drop for BusGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.taken.store(false, Ordering::Release);
+    }
+}
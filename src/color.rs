@@ -0,0 +1,142 @@
+//! Color types shared by the LED drivers
+//!
+//! Effects work in HSV. Colors are converted to RGB, gamma corrected and white balanced once,
+//! by the LED driver, so the same color looks the same on every kind of LED.
+
+/// 24 bit RGB color
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Creates a new color from its components
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+/// HSV color with 8 bit components
+///
+/// The hue wraps around, with 0 and 256 both being red.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct Hsv {
+    pub h: u8,
+    pub s: u8,
+    pub v: u8,
+}
+
+impl Hsv {
+    /// Creates a new color from its components
+    pub const fn new(h: u8, s: u8, v: u8) -> Self {
+        Self { h, s, v }
+    }
+    /// Adjusts the color, wrapping the hue and saturating the other components
+    pub const fn adjust(self, h: i8, s: i8, v: i8) -> Self {
+        const fn saturate(value: i16) -> u8 {
+            if value < 0 {
+                0
+            } else if value > 255 {
+                255
+            } else {
+                value as u8
+            }
+        }
+        Self {
+            h: (self.h as i16 + h as i16) as u8,
+            s: saturate(self.s as i16 + s as i16),
+            v: saturate(self.v as i16 + v as i16),
+        }
+    }
+    /// Converts the color to RGB
+    pub const fn to_rgb(self) -> Rgb {
+        let Hsv { h, s, v } = self;
+        if s == 0 {
+            return Rgb::new(v, v, v);
+        }
+        let (v, s) = (v as u16, s as u16);
+        let region = h / 43;
+        let remainder = (h - region * 43) as u16 * 6;
+        let p = ((v * (255 - s)) >> 8) as u8;
+        let q = ((v * (255 - ((s * remainder) >> 8))) >> 8) as u8;
+        let t = ((v * (255 - ((s * (255 - remainder)) >> 8))) >> 8) as u8;
+        let v = v as u8;
+        match region {
+            0 => Rgb::new(v, t, p),
+            1 => Rgb::new(q, v, p),
+            2 => Rgb::new(p, v, t),
+            3 => Rgb::new(p, q, v),
+            4 => Rgb::new(t, p, v),
+            _ => Rgb::new(v, p, q),
+        }
+    }
+}
+
+impl From<Hsv> for Rgb {
+    fn from(hsv: Hsv) -> Self {
+        hsv.to_rgb()
+    }
+}
+
+/// Gamma 2.2 lookup table, mapping perceived brightness to PWM duty
+#[rustfmt::skip]
+pub const GAMMA: [u8; 256] = [
+      0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   0,   1,
+      1,   1,   1,   1,   1,   1,   1,   1,   1,   2,   2,   2,   2,   2,   2,   2,
+      3,   3,   3,   3,   3,   4,   4,   4,   4,   5,   5,   5,   5,   6,   6,   6,
+      6,   7,   7,   7,   8,   8,   8,   9,   9,   9,  10,  10,  11,  11,  11,  12,
+     12,  13,  13,  13,  14,  14,  15,  15,  16,  16,  17,  17,  18,  18,  19,  19,
+     20,  20,  21,  22,  22,  23,  23,  24,  25,  25,  26,  26,  27,  28,  28,  29,
+     30,  30,  31,  32,  33,  33,  34,  35,  35,  36,  37,  38,  39,  39,  40,  41,
+     42,  43,  43,  44,  45,  46,  47,  48,  49,  49,  50,  51,  52,  53,  54,  55,
+     56,  57,  58,  59,  60,  61,  62,  63,  64,  65,  66,  67,  68,  69,  70,  71,
+     73,  74,  75,  76,  77,  78,  79,  81,  82,  83,  84,  85,  87,  88,  89,  90,
+     91,  93,  94,  95,  97,  98,  99, 100, 102, 103, 105, 106, 107, 109, 110, 111,
+    113, 114, 116, 117, 119, 120, 121, 123, 124, 126, 127, 129, 130, 132, 133, 135,
+    137, 138, 140, 141, 143, 145, 146, 148, 149, 151, 153, 154, 156, 158, 159, 161,
+    163, 165, 166, 168, 170, 172, 173, 175, 177, 179, 181, 182, 184, 186, 188, 190,
+    192, 194, 196, 197, 199, 201, 203, 205, 207, 209, 211, 213, 215, 217, 219, 221,
+    223, 225, 227, 229, 231, 234, 236, 238, 240, 242, 244, 246, 248, 251, 253, 255
+];
+
+/// Correction applied to colors right before they are sent to the LEDs
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct ColorCorrection {
+    /// Apply the gamma lookup table
+    pub gamma: bool,
+    /// Per-channel scale (0-255) that makes full white look neutral on the board's LEDs
+    pub white_balance: Rgb,
+}
+
+impl ColorCorrection {
+    /// Correction that leaves colors unchanged
+    pub const NONE: Self = Self {
+        gamma: false,
+        white_balance: Rgb::new(255, 255, 255),
+    };
+    /// Gamma correction without white balance
+    pub const GAMMA: Self = Self {
+        gamma: true,
+        white_balance: Rgb::new(255, 255, 255),
+    };
+    /// Applies the correction to a color
+    pub const fn apply(&self, color: Rgb) -> Rgb {
+        const fn channel(value: u8, gamma: bool, scale: u8) -> u8 {
+            let value = if gamma { GAMMA[value as usize] } else { value };
+            ((value as u16 * scale as u16 + 127) / 255) as u8
+        }
+        Rgb::new(
+            channel(color.r, self.gamma, self.white_balance.r),
+            channel(color.g, self.gamma, self.white_balance.g),
+            channel(color.b, self.gamma, self.white_balance.b),
+        )
+    }
+}
+
+impl Default for ColorCorrection {
+    fn default() -> Self {
+        Self::GAMMA
+    }
+}
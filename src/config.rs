@@ -0,0 +1,5 @@
+//! Board configuration
+//!
+//! Generated by the build script from `firmware.toml` (or the file named by `RKBFIRM_CONFIG`).
+
+include!(concat!(env!("OUT_DIR"), "/config.rs"));
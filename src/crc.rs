@@ -0,0 +1,169 @@
+//! Checksums
+//!
+//! Shared CRC implementations for everything that needs to detect corrupted data. The software
+//! implementations are table driven; chips with a CRC peripheral can implement [`Crc`] on top
+//! of it and be used interchangeably.
+
+/// A running CRC computation
+pub trait Crc {
+    /// Type of the checksum
+    type Output: Copy + Eq;
+    /// Restarts the computation
+    fn reset(&mut self);
+    /// Feeds data into the computation
+    fn update(&mut self, data: &[u8]);
+    /// Returns the checksum of the data fed in so far
+    fn finish(&self) -> Self::Output;
+    /// Computes the checksum of `data` in one go
+    fn checksum(&mut self, data: &[u8]) -> Self::Output {
+        self.reset();
+        self.update(data);
+        self.finish()
+    }
+}
+
+const fn crc16_table(poly: u16) -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+const fn crc32_table(poly: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Feeds `data` into a CRC-16 state
+const fn update16(table: &[u16; 256], mut state: u16, data: &[u8]) -> u16 {
+    let mut i = 0;
+    while i < data.len() {
+        state = (state << 8) ^ table[((state >> 8) as u8 ^ data[i]) as usize];
+        i += 1;
+    }
+    state
+}
+
+/// Feeds `data` into a reflected CRC-32 state
+const fn update32(table: &[u32; 256], mut state: u32, data: &[u8]) -> u32 {
+    let mut i = 0;
+    while i < data.len() {
+        state = (state >> 8) ^ table[(state as u8 ^ data[i]) as usize];
+        i += 1;
+    }
+    state
+}
+
+static CRC16_TABLE: [u16; 256] = crc16_table(0x1021);
+static CRC32_TABLE: [u32; 256] = crc32_table(0xEDB8_8320);
+
+// Standard check values: the checksum of the ASCII string "123456789"
+const _: () = assert!(update16(&crc16_table(0x1021), 0xFFFF, b"123456789") == 0x29B1);
+const _: () =
+    assert!(!update32(&crc32_table(0xEDB8_8320), 0xFFFF_FFFF, b"123456789") == 0xCBF4_3926);
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF)
+///
+/// Meant for short messages, e.g. on the split link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crc16 {
+    state: u16,
+}
+
+impl Crc16 {
+    /// Starts a new computation
+    pub const fn new() -> Self {
+        Self { state: 0xFFFF }
+    }
+}
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc for Crc16 {
+    type Output = u16;
+    fn reset(&mut self) {
+        self.state = 0xFFFF;
+    }
+    fn update(&mut self, data: &[u8]) {
+        self.state = update16(&CRC16_TABLE, self.state, data);
+    }
+    fn finish(&self) -> u16 {
+        self.state
+    }
+}
+
+/// CRC-32 as used by Ethernet and zlib (reflected polynomial 0xEDB88320)
+///
+/// Meant for larger blocks, e.g. settings in flash and firmware images.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    /// Starts a new computation
+    pub const fn new() -> Self {
+        Self { state: 0xFFFF_FFFF }
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc for Crc32 {
+    type Output = u32;
+    fn reset(&mut self) {
+        self.state = 0xFFFF_FFFF;
+    }
+    fn update(&mut self, data: &[u8]) {
+        self.state = update32(&CRC32_TABLE, self.state, data);
+    }
+    fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// Computes the CRC-16/CCITT-FALSE of `data`
+pub fn crc16(data: &[u8]) -> u16 {
+    Crc16::new().checksum(data)
+}
+
+/// Computes the CRC-32 of `data`
+pub fn crc32(data: &[u8]) -> u32 {
+    Crc32::new().checksum(data)
+}
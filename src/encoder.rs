@@ -0,0 +1,201 @@
+//! Rotary encoders
+//!
+//! Decodes quadrature encoders either per detent or in high resolution mode, where every edge
+//! counts as a step. High resolution steps line up with the HID resolution multiplier, so a
+//! host supporting high-resolution scrolling scrolls by `1 / resolution_multiplier()` lines per
+//! step.
+//!
+//! The host switches between the two modes through the resolution multiplier feature report.
+//! [`resolution_multiplier_descriptor`] describes that report, and
+//! [`Encoder::feature_report`]/[`Encoder::set_feature_report`] handle the get and set requests.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::v2::InputPin;
+
+/// Direction of a transition, indexed by `(previous state << 2) | current state`
+const TRANSITIONS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+/// Returns the HID report descriptor items of a resolution multiplier feature
+///
+/// The feature is a single byte: 0 reports one step per detent, 1 reports `edges_per_detent`
+/// steps per detent. The items belong inside the collection of the wheel they apply to.
+///
+/// # Panics
+/// This function panics if `edges_per_detent` is zero.
+#[rustfmt::skip]
+pub const fn resolution_multiplier_descriptor(edges_per_detent: u8) -> [u8; 22] {
+    assert!(edges_per_detent != 0, "edges per detent must not be zero");
+    [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x48, // Usage (Resolution Multiplier)
+        0x15, 0x00, // Logical Minimum (0)
+        0x25, 0x01, // Logical Maximum (1)
+        0x35, 0x01, // Physical Minimum (1)
+        0x45, edges_per_detent, // Physical Maximum (edges_per_detent)
+        0x75, 0x08, // Report Size (8)
+        0x95, 0x01, // Report Count (1)
+        0xB1, 0x02, // Feature (Data, Variable, Absolute)
+        0x35, 0x00, // Physical Minimum (0)
+        0x45, 0x00, // Physical Maximum (0)
+    ]
+}
+
+/// Counting mode of an encoder
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Resolution {
+    /// Report one step per detent, which is the given number of edges
+    Detent(u8),
+    /// Report one step per edge
+    High {
+        /// Number of edges per detent
+        edges_per_detent: u8,
+    },
+}
+
+impl Resolution {
+    /// Number of edges per detent
+    fn edges_per_detent(self) -> u8 {
+        match self {
+            Resolution::Detent(edges) => edges,
+            Resolution::High { edges_per_detent } => edges_per_detent,
+        }
+    }
+}
+
+/// Velocity-based acceleration
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Acceleration {
+    /// Steps slower than this (in µs) are not accelerated
+    pub slow_interval: u32,
+    /// Steps faster than this (in µs) use the full multiplier
+    pub fast_interval: u32,
+    /// Maximum step multiplier
+    pub max_multiplier: u8,
+}
+
+impl Acceleration {
+    /// Multiplier for a step that happened `interval` µs after the previous one
+    pub fn multiplier(&self, interval: u32) -> u8 {
+        if interval >= self.slow_interval || self.max_multiplier <= 1 {
+            1
+        } else if interval <= self.fast_interval {
+            self.max_multiplier
+        } else {
+            let range = self.slow_interval - self.fast_interval;
+            let extra =
+                u32::from(self.max_multiplier - 1) * (self.slow_interval - interval) / range;
+            1 + extra as u8
+        }
+    }
+}
+
+/// A quadrature encoder on two GPIO pins
+pub struct Encoder<A, B> {
+    a: A,
+    b: B,
+    state: u8,
+    edges: i16,
+    resolution: Resolution,
+    acceleration: Option<Acceleration>,
+    last_step: Option<u32>,
+}
+
+impl<A, B> Encoder<A, B>
+where
+    A: InputPin<Error = Infallible>,
+    B: InputPin<Error = Infallible>,
+{
+    /// Creates a new encoder counting one step per detent of four edges
+    pub fn new(a: A, b: B) -> Self {
+        let mut this = Self {
+            a,
+            b,
+            state: 0,
+            edges: 0,
+            resolution: Resolution::Detent(4),
+            acceleration: None,
+            last_step: None,
+        };
+        this.state = this.read_state();
+        this
+    }
+    /// Sets the counting mode
+    ///
+    /// # Panics
+    /// This function panics if the number of edges per detent is zero.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        assert!(
+            resolution.edges_per_detent() != 0,
+            "edges per detent must not be zero"
+        );
+        self.resolution = resolution;
+        self.edges = 0;
+    }
+    /// Enables or disables acceleration
+    pub fn set_acceleration(&mut self, acceleration: Option<Acceleration>) {
+        self.acceleration = acceleration;
+        self.last_step = None;
+    }
+    /// Number of steps per detent
+    pub fn resolution_multiplier(&self) -> u8 {
+        match self.resolution {
+            Resolution::Detent(_) => 1,
+            Resolution::High { edges_per_detent } => edges_per_detent,
+        }
+    }
+    /// Returns the value of the resolution multiplier feature report
+    pub fn feature_report(&self) -> u8 {
+        u8::from(matches!(self.resolution, Resolution::High { .. }))
+    }
+    /// Handles a set request for the resolution multiplier feature report
+    ///
+    /// A non-zero value switches to high resolution mode, zero switches back to one step per
+    /// detent. The number of edges per detent is kept.
+    pub fn set_feature_report(&mut self, value: u8) {
+        let edges_per_detent = self.resolution.edges_per_detent();
+        self.set_resolution(if value == 0 {
+            Resolution::Detent(edges_per_detent)
+        } else {
+            Resolution::High { edges_per_detent }
+        });
+    }
+    /// Releases the pins
+    pub fn free(self) -> (A, B) {
+        (self.a, self.b)
+    }
+    fn read_state(&self) -> u8 {
+        (u8::from(self.a.is_high().unwrap()) << 1) | u8::from(self.b.is_high().unwrap())
+    }
+    /// Samples the pins and returns the number of steps since the last update
+    ///
+    /// Positive values are clockwise. `now` is a free-running microsecond timestamp.
+    pub fn update(&mut self, now: u32) -> i16 {
+        let state = self.read_state();
+        let direction = TRANSITIONS[usize::from((self.state << 2) | state)];
+        self.state = state;
+        if direction == 0 {
+            return 0;
+        }
+        let steps = match self.resolution {
+            Resolution::High { .. } => direction,
+            Resolution::Detent(edges) => {
+                if self.edges.signum() != i16::from(direction) {
+                    self.edges = 0;
+                }
+                self.edges += i16::from(direction);
+                if self.edges.unsigned_abs() < u16::from(edges) {
+                    return 0;
+                }
+                self.edges = 0;
+                direction
+            }
+        };
+        let multiplier = match (self.acceleration, self.last_step) {
+            (Some(acceleration), Some(last)) => acceleration.multiplier(now.wrapping_sub(last)),
+            _ => 1,
+        };
+        self.last_step = Some(now);
+        i16::from(steps) * i16::from(multiplier)
+    }
+}
@@ -0,0 +1,150 @@
+//! Filters for noisy samples
+//!
+//! Building blocks for smoothing ADC readings and sensor input.
+
+/// Integer type that can be filtered by [`Ema`]
+pub trait Sample: Copy {
+    /// Type wide enough to hold a sample with additional fractional bits
+    type Wide: Copy;
+}
+
+/// Exponential moving average
+///
+/// Each new sample is weighted with `1 / 2^shift`, so larger shifts filter more strongly but
+/// follow changes more slowly.
+#[derive(Copy, Clone, Debug)]
+pub struct Ema<T: Sample> {
+    acc: T::Wide,
+    shift: u8,
+    primed: bool,
+}
+
+macro_rules! ema {
+    ($($ty:ty => $wide:ty),*) => {
+        $(
+            impl Sample for $ty {
+                type Wide = $wide;
+            }
+
+            impl Ema<$ty> {
+                /// Creates a new filter
+                ///
+                /// # Panics
+                /// This function panics if `shift` leaves no room for the sample in the
+                /// accumulator.
+                pub const fn new(shift: u8) -> Self {
+                    assert!(
+                        (shift as u32) < <$wide>::BITS - <$ty>::BITS,
+                        "shift too large for sample type"
+                    );
+                    Self {
+                        acc: 0,
+                        shift,
+                        primed: false,
+                    }
+                }
+                /// Feeds a sample into the filter and returns the filtered value
+                ///
+                /// The first sample initializes the filter, so it does not ramp up from 0.
+                pub fn update(&mut self, sample: $ty) -> $ty {
+                    let sample = <$wide>::from(sample);
+                    if self.primed {
+                        self.acc = self.acc - (self.acc >> self.shift) + sample;
+                    } else {
+                        self.acc = sample << self.shift;
+                        self.primed = true;
+                    }
+                    self.value()
+                }
+                /// Returns the filtered value
+                pub fn value(&self) -> $ty {
+                    (self.acc >> self.shift) as $ty
+                }
+                /// Resets the filter, so the next sample initializes it again
+                pub fn reset(&mut self) {
+                    self.primed = false;
+                }
+            }
+        )*
+    };
+}
+
+ema!(u8 => u16, u16 => u32, u32 => u64, i8 => i16, i16 => i32, i32 => i64);
+
+/// Schmitt trigger
+///
+/// Turns on once a sample rises above the upper threshold and turns off once it falls below the
+/// lower threshold, so a noisy sample near a single threshold does not toggle the output.
+#[derive(Copy, Clone, Debug)]
+pub struct Hysteresis<T> {
+    low: T,
+    high: T,
+    state: bool,
+}
+
+impl<T: PartialOrd> Hysteresis<T> {
+    /// Creates a new trigger that starts off
+    ///
+    /// # Panics
+    /// This function panics if `low` is greater than `high`.
+    pub fn new(low: T, high: T) -> Self {
+        assert!(low <= high, "lower threshold above upper threshold");
+        Self {
+            low,
+            high,
+            state: false,
+        }
+    }
+    /// Feeds a sample into the trigger and returns the new state
+    pub fn update(&mut self, sample: T) -> bool {
+        if self.state {
+            if sample < self.low {
+                self.state = false;
+            }
+        } else if sample > self.high {
+            self.state = true;
+        }
+        self.state
+    }
+    /// Returns the current state
+    pub fn state(&self) -> bool {
+        self.state
+    }
+}
+
+/// Median of the last three samples
+///
+/// Removes single-sample spikes without the lag of an averaging filter.
+#[derive(Copy, Clone, Debug)]
+pub struct Median3<T> {
+    history: [T; 2],
+    len: u8,
+}
+
+impl<T: Ord + Copy + Default> Median3<T> {
+    /// Creates a new filter
+    pub fn new() -> Self {
+        Self {
+            history: [T::default(); 2],
+            len: 0,
+        }
+    }
+    /// Feeds a sample into the filter and returns the filtered value
+    ///
+    /// Until three samples have been seen, the latest sample is returned unchanged.
+    pub fn update(&mut self, sample: T) -> T {
+        let [a, b] = self.history;
+        self.history = [b, sample];
+        if self.len < 2 {
+            self.len += 1;
+            return sample;
+        }
+        a.max(b).min(a.min(b).max(sample))
+    }
+}
+
+impl<T: Ord + Copy + Default> Default for Median3<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
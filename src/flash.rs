@@ -0,0 +1,287 @@
+//! Low-level flash access
+//!
+//! [`Flash`] describes a region of flash that can be read, programmed and erased. On the RP2040
+//! the firmware executes in place from the same chip, so there is no read-while-write: every
+//! program or erase disables interrupts and runs from RAM until execute-in-place is restored.
+
+use core::{
+    ops::Range,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+/// Error returned by flash operations
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum FlashError {
+    /// The range is not inside the flash region
+    OutOfBounds,
+    /// The range is not aligned to the page or erase size
+    NotAligned,
+}
+
+/// A region of flash memory
+pub trait Flash {
+    /// Smallest unit that can be programmed, in bytes
+    const PAGE_SIZE: usize;
+    /// Smallest unit that can be erased, in bytes
+    const ERASE_SIZE: usize;
+
+    /// Returns the size of the region in bytes
+    fn capacity(&self) -> usize;
+    /// Reads `buf.len()` bytes starting at `offset`
+    ///
+    /// # Errors
+    /// This function returns an error if the range is out of bounds.
+    fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), FlashError>;
+    /// Programs `data` starting at `offset`
+    ///
+    /// The range has to be erased before, and `offset` and the length of `data` have to be
+    /// multiples of [`PAGE_SIZE`](Self::PAGE_SIZE).
+    ///
+    /// # Errors
+    /// This function returns an error if the range is out of bounds or not aligned.
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), FlashError>;
+    /// Erases the given range, which has to be aligned to [`ERASE_SIZE`](Self::ERASE_SIZE)
+    ///
+    /// # Errors
+    /// This function returns an error if the range is out of bounds or not aligned.
+    fn erase(&mut self, range: Range<u32>) -> Result<(), FlashError>;
+}
+
+/// Checks that `offset..offset + len` is inside `capacity` and aligned to `align`
+fn check(offset: u32, len: usize, align: usize, capacity: usize) -> Result<(), FlashError> {
+    let offset = offset as usize;
+    match offset.checked_add(len) {
+        Some(end) if end <= capacity => {}
+        _ => return Err(FlashError::OutOfBounds),
+    }
+    if offset % align != 0 || len % align != 0 {
+        return Err(FlashError::NotAligned);
+    }
+    Ok(())
+}
+
+/// Start of the execute-in-place window
+const XIP_BASE: usize = 0x1000_0000;
+/// Size of the second stage bootloader at the start of flash
+const BOOT2_SIZE: usize = 256;
+/// Size of a 64 kiB erase block
+const BLOCK_SIZE: u32 = 1 << 16;
+/// Command for erasing a 64 kiB block
+const BLOCK_ERASE_CMD: u8 = 0xD8;
+
+extern "C" {
+    /// End of the firmware image in flash, defined in `memory.x`
+    static __flash_binary_end: u8;
+}
+
+/// Copy of the second stage bootloader, which restores fast execute-in-place after flash
+/// operations
+static mut BOOT2: [u32; BOOT2_SIZE / 4] = [0; BOOT2_SIZE / 4];
+
+/// Flash functions of the boot ROM
+///
+/// The pointers are looked up before execute-in-place is disabled, as the lookup code lives in
+/// flash.
+struct RomFns {
+    connect_internal_flash: RomFn,
+    flash_exit_xip: RomFn,
+    flash_range_erase: EraseFn,
+    flash_range_program: ProgramFn,
+    flash_flush_cache: RomFn,
+}
+
+type RomFn = unsafe extern "C" fn();
+type EraseFn = unsafe extern "C" fn(u32, usize, u32, u8);
+type ProgramFn = unsafe extern "C" fn(u32, *const u8, usize);
+
+impl RomFns {
+    /// Looks up the functions in the ROM function table
+    unsafe fn lookup() -> Self {
+        Self {
+            connect_internal_flash: core::mem::transmute::<*const (), RomFn>(rom_fn(*b"IF")),
+            flash_exit_xip: core::mem::transmute::<*const (), RomFn>(rom_fn(*b"EX")),
+            flash_range_erase: core::mem::transmute::<*const (), EraseFn>(rom_fn(*b"RE")),
+            flash_range_program: core::mem::transmute::<*const (), ProgramFn>(rom_fn(*b"RP")),
+            flash_flush_cache: core::mem::transmute::<*const (), RomFn>(rom_fn(*b"FC")),
+        }
+    }
+}
+
+/// Looks up a function in the boot ROM by its two letter tag
+unsafe fn rom_fn(tag: [u8; 2]) -> *const () {
+    type LookupFn = unsafe extern "C" fn(*const u16, u32) -> *const ();
+    let table = usize::from(*(0x14 as *const u16)) as *const u16;
+    let lookup = core::mem::transmute::<usize, LookupFn>(usize::from(*(0x18 as *const u16)));
+    lookup(table, u32::from(u16::from_le_bytes(tag)))
+}
+
+/// Operation executed from RAM
+enum Op {
+    Erase,
+    Program(*const u8),
+}
+
+/// Runs a flash operation with execute-in-place disabled
+///
+/// # Safety
+/// This function and everything it calls must not touch flash. Interrupts have to be disabled
+/// and the other core must not execute from flash.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn flash_op(rom: &RomFns, op: Op, addr: u32, len: usize) {
+    compiler_fence(Ordering::SeqCst);
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    if let Op::Program(data) = op {
+        (rom.flash_range_program)(addr, data, len);
+    } else {
+        (rom.flash_range_erase)(addr, len, BLOCK_SIZE, BLOCK_ERASE_CMD);
+    }
+    (rom.flash_flush_cache)();
+    let boot2 = core::mem::transmute::<usize, RomFn>(core::ptr::addr_of!(BOOT2) as usize + 1);
+    boot2();
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Checks that a region of the flash chip is aligned to sectors and starts after the second
+/// stage bootloader and the firmware image, which ends at `binary_end`
+fn check_region(range: &Range<u32>, binary_end: usize) {
+    let erase_size = <Rp2040Flash as Flash>::ERASE_SIZE as u32;
+    assert!(
+        range.start % erase_size == 0 && range.end % erase_size == 0,
+        "flash region is not aligned to sectors"
+    );
+    assert!(
+        range.start as usize >= BOOT2_SIZE && range.start <= range.end,
+        "invalid flash region"
+    );
+    assert!(
+        range.start as usize >= binary_end,
+        "flash region overlaps the firmware"
+    );
+}
+
+/// Region of the RP2040's QSPI flash
+///
+/// Only core 0 may run while the region is written to, and the region must not overlap the
+/// firmware.
+pub struct Rp2040Flash {
+    start: u32,
+    len: u32,
+}
+
+impl Rp2040Flash {
+    /// Creates a driver for the given byte range of the flash chip
+    ///
+    /// # Panics
+    /// This function panics if the range is not aligned to [`ERASE_SIZE`](Flash::ERASE_SIZE)
+    /// or overlaps the second stage bootloader or the firmware image.
+    pub fn new(range: Range<u32>) -> Self {
+        let binary_end = unsafe { &__flash_binary_end as *const u8 as usize } - XIP_BASE;
+        check_region(&range, binary_end);
+        Self {
+            start: range.start,
+            len: range.end - range.start,
+        }
+    }
+    /// Runs `op` on `len` bytes starting at `offset` into the region
+    fn run(&mut self, op: Op, offset: u32, len: usize) {
+        cortex_m::interrupt::free(|_| unsafe {
+            let boot2 = core::ptr::addr_of_mut!(BOOT2).cast::<u32>();
+            core::ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2, BOOT2_SIZE / 4);
+            let rom = RomFns::lookup();
+            flash_op(&rom, op, self.start + offset, len);
+        });
+    }
+}
+
+impl Flash for Rp2040Flash {
+    const PAGE_SIZE: usize = 256;
+    const ERASE_SIZE: usize = 4096;
+
+    fn capacity(&self) -> usize {
+        self.len as usize
+    }
+    fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), FlashError> {
+        check(offset, buf.len(), 1, self.capacity())?;
+        let src = (XIP_BASE + (self.start + offset) as usize) as *const u8;
+        unsafe { core::ptr::copy_nonoverlapping(src, buf.as_mut_ptr(), buf.len()) };
+        Ok(())
+    }
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), FlashError> {
+        check(offset, data.len(), Self::PAGE_SIZE, self.capacity())?;
+        // `data` may itself live in flash, so each page is staged in RAM
+        let mut page = [0; Self::PAGE_SIZE];
+        for (i, chunk) in data.chunks(Self::PAGE_SIZE).enumerate() {
+            page.copy_from_slice(chunk);
+            let offset = offset + (i * Self::PAGE_SIZE) as u32;
+            self.run(Op::Program(page.as_ptr()), offset, Self::PAGE_SIZE);
+        }
+        Ok(())
+    }
+    fn erase(&mut self, range: Range<u32>) -> Result<(), FlashError> {
+        let len = range.end.saturating_sub(range.start) as usize;
+        check(range.start, len, Self::ERASE_SIZE, self.capacity())?;
+        if len != 0 {
+            self.run(Op::Erase, range.start, len);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTOR: u32 = 4096;
+
+    #[test]
+    fn range_inside_capacity() {
+        assert_eq!(check(0, 256, 256, 1024), Ok(()));
+        assert_eq!(check(768, 256, 256, 1024), Ok(()));
+        assert_eq!(check(1024, 0, 256, 1024), Ok(()));
+        assert_eq!(check(1024, 256, 256, 1024), Err(FlashError::OutOfBounds));
+        assert_eq!(check(768, 512, 256, 1024), Err(FlashError::OutOfBounds));
+        assert_eq!(
+            check(u32::MAX, usize::MAX, 1, usize::MAX),
+            Err(FlashError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn range_alignment() {
+        assert_eq!(check(3, 5, 1, 1024), Ok(()));
+        assert_eq!(check(128, 256, 256, 1024), Err(FlashError::NotAligned));
+        assert_eq!(check(256, 128, 256, 1024), Err(FlashError::NotAligned));
+    }
+
+    #[test]
+    fn region_after_firmware() {
+        check_region(&(4 * SECTOR..8 * SECTOR), 3 * SECTOR as usize + 1);
+        check_region(&(4 * SECTOR..4 * SECTOR), 4 * SECTOR as usize);
+    }
+
+    #[test]
+    #[should_panic(expected = "flash region overlaps the firmware")]
+    fn region_overlapping_firmware() {
+        check_region(&(4 * SECTOR..8 * SECTOR), 4 * SECTOR as usize + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid flash region")]
+    fn region_overlapping_bootloader() {
+        check_region(&(0..SECTOR), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid flash region")]
+    fn reversed_region() {
+        check_region(&(2 * SECTOR..SECTOR), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "flash region is not aligned to sectors")]
+    fn unaligned_region() {
+        check_region(&(SECTOR..SECTOR + 256), 0);
+    }
+}
@@ -0,0 +1,333 @@
+//! Shared I2C bus
+//!
+//! Lets several drivers (display, LED drivers, expanders, haptics) share one I2C peripheral.
+//! Every driver gets its own [`I2cDevice`] handle implementing the blocking I2C traits, which
+//! keeps error statistics for that driver. The peripheral is handed out through a
+//! [`BusMutex`], so a bus in a `static` can also be used from interrupt handlers or the other
+//! core without disabling interrupts for a whole transaction. A transaction that finds the bus
+//! taken fails with [`Error::Busy`] instead of blocking the scan loop.
+//!
+//! A bus created with [`I2cBus::with_recovery`] frees a stuck SDA line on its own once a device
+//! failed [`RECOVERY_THRESHOLD`] times in a row.
+
+use core::convert::Infallible;
+
+use embedded_hal::{
+    blocking::{
+        delay::DelayUs,
+        i2c::{Read, Write, WriteRead},
+    },
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::bus::{BusMutex, Busy};
+
+/// Number of consecutive errors after which [`I2cDevice::needs_recovery`] reports a stuck bus
+pub const RECOVERY_THRESHOLD: u16 = 3;
+
+/// Errors returned by a device on a shared bus
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Error<E> {
+    /// The bus is used by someone else
+    Busy,
+    /// The I2C transaction failed
+    I2c(E),
+}
+
+impl<E> From<Busy> for Error<E> {
+    fn from(_: Busy) -> Self {
+        Self::Busy
+    }
+}
+
+/// Pins of a bus, used to recover it while the peripheral is idle
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct BusPins {
+    scl: u8,
+    sda: u8,
+    sys_clk_hz: u32,
+}
+
+impl BusPins {
+    /// Creates the pins of a bus, with the system clock in Hz to time the recovery clock pulses
+    ///
+    /// # Panics
+    /// This function panics if a pin is not a GPIO of bank 0 or both pins are the same.
+    ///
+    /// # Safety
+    /// `scl` and `sda` have to be the pins the I2C peripheral of the bus is configured on. Their
+    /// function and SIO output are changed during recovery, so nothing else may use them while
+    /// the bus exists.
+    pub const unsafe fn new(scl: u8, sda: u8, sys_clk_hz: u32) -> Self {
+        assert!(scl < GPIO_COUNT && sda < GPIO_COUNT, "not a GPIO of bank 0");
+        assert!(scl != sda, "SCL and SDA have to be different pins");
+        Self {
+            scl,
+            sda,
+            sys_clk_hz,
+        }
+    }
+    /// Switches the pins to GPIO, runs [`recover`] and hands them back to the I2C peripheral
+    ///
+    /// Returns `true` if SDA is released.
+    fn recover(&self) -> bool {
+        // SAFETY: the pins belong to the bus, whose peripheral is not using them right now
+        let (mut scl, mut sda) = unsafe { (SioPin::new(self.scl), SioPin::new(self.sda)) };
+        scl.select(FUNC_SIO);
+        sda.select(FUNC_SIO);
+        let mut delay = CycleDelay {
+            cycles_per_us: self.sys_clk_hz / 1_000_000,
+        };
+        let released = recover(&mut scl, &mut sda, &mut delay);
+        scl.select(FUNC_I2C);
+        sda.select(FUNC_I2C);
+        released
+    }
+}
+
+/// An I2C peripheral shared by several devices
+pub struct I2cBus<I2C> {
+    i2c: BusMutex<I2C>,
+    pins: Option<BusPins>,
+}
+
+impl<I2C> I2cBus<I2C> {
+    /// Creates a new shared bus without automatic recovery
+    pub const fn new(i2c: I2C) -> Self {
+        Self {
+            i2c: BusMutex::new(i2c),
+            pins: None,
+        }
+    }
+    /// Creates a new shared bus that recovers a stuck SDA line on `pins`
+    pub const fn with_recovery(i2c: I2C, pins: BusPins) -> Self {
+        Self {
+            i2c: BusMutex::new(i2c),
+            pins: Some(pins),
+        }
+    }
+    /// Creates a handle for a device on the bus
+    pub fn device(&self) -> I2cDevice<'_, I2C> {
+        I2cDevice {
+            bus: self,
+            stats: DeviceStats::default(),
+        }
+    }
+    /// Runs `f` with exclusive access to the peripheral
+    ///
+    /// # Errors
+    /// This function returns [`Busy`] if the bus is used by someone else, including a caller
+    /// of `f`.
+    pub fn try_with<R>(&self, f: impl FnOnce(&mut I2C) -> R) -> Result<R, Busy> {
+        Ok(f(&mut *self.i2c.try_lock()?))
+    }
+    /// Releases the peripheral
+    pub fn free(self) -> I2C {
+        self.i2c.into_inner()
+    }
+}
+
+/// Transaction statistics of a device
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct DeviceStats {
+    /// Number of transactions
+    pub transactions: u32,
+    /// Number of failed transactions
+    pub errors: u32,
+    /// Number of failed transactions since the last successful one
+    pub consecutive_errors: u16,
+    /// Number of successful bus recoveries after errors of this device
+    pub recoveries: u16,
+}
+
+/// Handle for a device on a shared bus
+pub struct I2cDevice<'a, I2C> {
+    bus: &'a I2cBus<I2C>,
+    stats: DeviceStats,
+}
+
+impl<I2C> I2cDevice<'_, I2C> {
+    /// Returns the transaction statistics of the device
+    pub fn stats(&self) -> &DeviceStats {
+        &self.stats
+    }
+    /// Returns `true` if the device failed often enough in a row that the bus may be stuck
+    pub fn needs_recovery(&self) -> bool {
+        self.stats.consecutive_errors >= RECOVERY_THRESHOLD
+    }
+    /// Clears the consecutive error count, e.g. after recovering the bus
+    pub fn clear_errors(&mut self) {
+        self.stats.consecutive_errors = 0;
+    }
+    fn transaction<E>(
+        &mut self,
+        f: impl FnOnce(&mut I2C) -> Result<(), E>,
+    ) -> Result<(), Error<E>> {
+        let mut i2c = self.bus.i2c.try_lock()?;
+        let result = f(&mut *i2c);
+        self.stats.transactions = self.stats.transactions.wrapping_add(1);
+        if result.is_ok() {
+            self.stats.consecutive_errors = 0;
+        } else {
+            self.stats.errors = self.stats.errors.saturating_add(1);
+            self.stats.consecutive_errors = self.stats.consecutive_errors.saturating_add(1);
+            if let (true, Some(pins)) = (self.needs_recovery(), self.bus.pins) {
+                // Holding the peripheral keeps other devices off the bus during recovery
+                if pins.recover() {
+                    self.stats.recoveries = self.stats.recoveries.saturating_add(1);
+                    self.clear_errors();
+                }
+            }
+        }
+        result.map_err(Error::I2c)
+    }
+}
+
+impl<I2C: Write> Write for I2cDevice<'_, I2C> {
+    type Error = Error<I2C::Error>;
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.transaction(|i2c| i2c.write(address, bytes))
+    }
+}
+
+impl<I2C: Read> Read for I2cDevice<'_, I2C> {
+    type Error = Error<I2C::Error>;
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.transaction(|i2c| i2c.read(address, buffer))
+    }
+}
+
+impl<I2C: WriteRead> WriteRead for I2cDevice<'_, I2C> {
+    type Error = Error<I2C::Error>;
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.transaction(|i2c| i2c.write_read(address, bytes, buffer))
+    }
+}
+
+/// Frees a bus whose SDA line is held low by a device
+///
+/// A device that was interrupted in the middle of a read keeps driving SDA until it has clocked
+/// out the rest of its byte. This clocks SCL up to 9 times at roughly 100 kHz until SDA is
+/// released, then generates a STOP condition so the devices wait for the next START. The pins
+/// have to be switched from the I2C function to open drain GPIO before, and back afterwards.
+///
+/// Returns `true` if SDA is released.
+pub fn recover<SCL, SDA, D>(scl: &mut SCL, sda: &mut SDA, delay: &mut D) -> bool
+where
+    SCL: OutputPin<Error = Infallible>,
+    SDA: InputPin<Error = Infallible> + OutputPin<Error = Infallible>,
+    D: DelayUs<u32>,
+{
+    scl.set_high().unwrap();
+    delay.delay_us(5);
+    for _ in 0..9 {
+        if sda.is_high().unwrap() {
+            break;
+        }
+        scl.set_low().unwrap();
+        delay.delay_us(5);
+        scl.set_high().unwrap();
+        delay.delay_us(5);
+    }
+    if sda.is_low().unwrap() {
+        return false;
+    }
+    // STOP: SDA rises while SCL is high
+    scl.set_low().unwrap();
+    delay.delay_us(5);
+    sda.set_low().unwrap();
+    delay.delay_us(5);
+    scl.set_high().unwrap();
+    delay.delay_us(5);
+    sda.set_high().unwrap();
+    delay.delay_us(5);
+    sda.is_high().unwrap()
+}
+
+/// Number of GPIOs in bank 0
+const GPIO_COUNT: u8 = 30;
+/// Control registers of the GPIOs in bank 0, 8 bytes apart
+const IO_BANK0_CTRL: usize = 0x4001_4004;
+/// Function select of the I2C peripherals
+const FUNC_I2C: u32 = 3;
+/// Function select of software controlled GPIO
+const FUNC_SIO: u32 = 5;
+const SIO_GPIO_IN: usize = 0xD000_0004;
+const SIO_GPIO_OUT_CLR: usize = 0xD000_0018;
+const SIO_GPIO_OE_SET: usize = 0xD000_0024;
+const SIO_GPIO_OE_CLR: usize = 0xD000_0028;
+
+/// Open drain GPIO driven through SIO, independent of the pin types of the HAL
+///
+/// The pins stay owned by the I2C peripheral; only their function is switched for the duration
+/// of the recovery. The SIO set and clear registers only affect the bits written as 1, so
+/// driving one pin does not race with the other core driving other pins.
+struct SioPin {
+    gpio: u8,
+}
+
+impl SioPin {
+    /// Releases the pin and selects low as the level it drives
+    ///
+    /// # Safety
+    /// `gpio` has to be a GPIO of bank 0 that nothing else uses while the pin exists.
+    unsafe fn new(gpio: u8) -> Self {
+        let this = Self { gpio };
+        // SIO registers are always mapped, and only the bit of the pin is written
+        (SIO_GPIO_OE_CLR as *mut u32).write_volatile(this.mask());
+        (SIO_GPIO_OUT_CLR as *mut u32).write_volatile(this.mask());
+        this
+    }
+    fn mask(&self) -> u32 {
+        1 << self.gpio
+    }
+    /// Selects the peripheral function of the pin
+    fn select(&self, function: u32) {
+        let ctrl = (IO_BANK0_CTRL + 8 * usize::from(self.gpio)) as *mut u32;
+        // SAFETY: `gpio` is below `GPIO_COUNT`, so `ctrl` is the control register of the pin,
+        // which is not used by anyone else
+        unsafe { ctrl.write_volatile((ctrl.read_volatile() & !0x1F) | function) };
+    }
+}
+
+impl OutputPin for SioPin {
+    type Error = Infallible;
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        // SAFETY: SIO registers are always mapped, and only the bit of the pin is written
+        unsafe { (SIO_GPIO_OE_SET as *mut u32).write_volatile(self.mask()) };
+        Ok(())
+    }
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        // The pull-ups of the bus pull the line high
+        // SAFETY: SIO registers are always mapped, and only the bit of the pin is written
+        unsafe { (SIO_GPIO_OE_CLR as *mut u32).write_volatile(self.mask()) };
+        Ok(())
+    }
+}
+
+impl InputPin for SioPin {
+    type Error = Infallible;
+    fn is_high(&self) -> Result<bool, Infallible> {
+        // SAFETY: SIO registers are always mapped, and reading the input has no side effects
+        Ok(unsafe { (SIO_GPIO_IN as *const u32).read_volatile() } & self.mask() != 0)
+    }
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(!self.is_high()?)
+    }
+}
+
+/// Busy-waiting delay based on the system clock
+struct CycleDelay {
+    cycles_per_us: u32,
+}
+
+impl DelayUs<u32> for CycleDelay {
+    fn delay_us(&mut self, us: u32) {
+        cortex_m::asm::delay(us.saturating_mul(self.cycles_per_us));
+    }
+}
@@ -0,0 +1,139 @@
+//! Indicator LEDs
+//!
+//! Maps logical indicators onto discrete LEDs, for boards without RGB lighting.
+
+use core::convert::Infallible;
+
+use embedded_hal::{digital::v2::OutputPin, PwmPin};
+
+/// Logical indicators
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Indicator {
+    NumLock,
+    CapsLock,
+    ScrollLock,
+    /// The given layer is active
+    Layer(u8),
+    BleConnected,
+    LowBattery,
+}
+
+/// An LED that can display an indicator
+pub trait IndicatorLed {
+    /// Sets the brightness of the LED (0-255)
+    fn set_level(&mut self, level: u8);
+}
+
+/// Indicator LED on a plain GPIO pin
+///
+/// Any non-zero level turns the LED on.
+pub struct GpioLed<P> {
+    pin: P,
+    active_low: bool,
+}
+
+impl<P: OutputPin<Error = Infallible>> GpioLed<P> {
+    /// Creates an LED that lights up when the pin is driven high
+    pub fn new(pin: P) -> Self {
+        Self {
+            pin,
+            active_low: false,
+        }
+    }
+    /// Creates an LED that lights up when the pin is driven low
+    pub fn new_active_low(pin: P) -> Self {
+        Self {
+            pin,
+            active_low: true,
+        }
+    }
+    /// Releases the pin
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: OutputPin<Error = Infallible>> IndicatorLed for GpioLed<P> {
+    fn set_level(&mut self, level: u8) {
+        if (level != 0) != self.active_low {
+            self.pin.set_high().unwrap();
+        } else {
+            self.pin.set_low().unwrap();
+        }
+    }
+}
+
+/// Indicator LED on a PWM channel
+pub struct PwmLed<P> {
+    pin: P,
+}
+
+impl<P: PwmPin<Duty = u16>> PwmLed<P> {
+    /// Creates a new LED, enabling the PWM channel
+    pub fn new(mut pin: P) -> Self {
+        pin.set_duty(0);
+        pin.enable();
+        Self { pin }
+    }
+    /// Releases the PWM channel
+    pub fn free(mut self) -> P {
+        self.pin.disable();
+        self.pin
+    }
+}
+
+impl<P: PwmPin<Duty = u16>> IndicatorLed for PwmLed<P> {
+    fn set_level(&mut self, level: u8) {
+        let duty = u32::from(self.pin.get_max_duty()) * u32::from(level) / 255;
+        self.pin.set_duty(duty as u16);
+    }
+}
+
+/// Table of indicator LEDs declared by a board
+pub struct Indicators<'a, const N: usize> {
+    leds: [(Indicator, &'a mut dyn IndicatorLed); N],
+    active: [bool; N],
+    brightness: u8,
+}
+
+impl<'a, const N: usize> Indicators<'a, N> {
+    /// Creates a new table, with all indicators turned off
+    pub fn new(leds: [(Indicator, &'a mut dyn IndicatorLed); N]) -> Self {
+        let mut this = Self {
+            leds,
+            active: [false; N],
+            brightness: u8::MAX,
+        };
+        this.refresh();
+        this
+    }
+    /// Turns an indicator on or off
+    ///
+    /// Indicators that are not mapped to an LED are ignored.
+    pub fn set(&mut self, indicator: Indicator, on: bool) {
+        for (i, (mapped, led)) in self.leds.iter_mut().enumerate() {
+            if *mapped == indicator && self.active[i] != on {
+                self.active[i] = on;
+                led.set_level(if on { self.brightness } else { 0 });
+            }
+        }
+    }
+    /// Returns `true` if any LED mapped to the indicator is lit
+    pub fn is_set(&self, indicator: Indicator) -> bool {
+        self.leds
+            .iter()
+            .zip(self.active.iter())
+            .any(|((mapped, _), &on)| *mapped == indicator && on)
+    }
+    /// Sets the brightness of lit indicators (0-255)
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+        self.refresh();
+    }
+    /// Writes the current state to all LEDs
+    fn refresh(&mut self) {
+        for ((_, led), &on) in self.leds.iter_mut().zip(self.active.iter()) {
+            led.set_level(if on { self.brightness } else { 0 });
+        }
+    }
+}
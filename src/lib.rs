@@ -0,0 +1,28 @@
+//! RKB1 firmware library
+//!
+//! Drivers and building blocks used by the firmware. `main.rs` only does the board bring-up.
+#![cfg_attr(not(test), no_std)]
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+
+pub mod apa102;
+pub mod assets;
+pub mod bitset;
+pub mod bus;
+pub mod color;
+pub mod config;
+pub mod crc;
+pub mod encoder;
+pub mod filter;
+pub mod fixed;
+pub mod flash;
+pub mod i2c_bus;
+pub mod indicator;
+pub mod pointing;
+pub mod rng;
+pub mod scan_monitor;
+pub mod slider;
+pub mod spi_bus;
+pub mod st7789;
+pub mod store;
+pub mod touch;
@@ -0,0 +1,163 @@
+//! Pointing device input processing
+//!
+//! Motion reports from a trackball or trackpad sensor are smoothed, rotated to match how the
+//! sensor is mounted, optionally snapped to the axes and scaled down in precision mode. Sub-count
+//! motion is carried over to the next report, so slow movement isn't lost to rounding.
+
+use crate::{
+    filter::Ema,
+    fixed::{self, I16F16},
+};
+
+/// Configuration of the input processing
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct PointingConfig {
+    /// Clockwise rotation of the sensor in degrees
+    pub rotation: i16,
+    /// Motion within this many degrees of an axis is snapped to the axis (0 disables snapping)
+    pub snap_angle: u8,
+    /// Divisor applied to the motion in precision mode
+    pub precision_divisor: u8,
+    /// Strength of the smoothing filter, as the shift of a moving average (0 disables it)
+    pub smoothing: u8,
+}
+
+impl PointingConfig {
+    /// Configuration that passes motion through unchanged, halving it in precision mode
+    pub const fn new() -> Self {
+        Self {
+            rotation: 0,
+            snap_angle: 0,
+            precision_divisor: 2,
+            smoothing: 0,
+        }
+    }
+    /// Sets the rotation of the sensor
+    pub const fn rotation(mut self, rotation: i16) -> Self {
+        self.rotation = rotation;
+        self
+    }
+    /// Sets the angle snapping threshold
+    pub const fn snap_angle(mut self, snap_angle: u8) -> Self {
+        self.snap_angle = snap_angle;
+        self
+    }
+    /// Sets the precision mode divisor
+    pub const fn precision_divisor(mut self, precision_divisor: u8) -> Self {
+        self.precision_divisor = precision_divisor;
+        self
+    }
+    /// Sets the smoothing strength
+    pub const fn smoothing(mut self, smoothing: u8) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+}
+
+impl Default for PointingConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Processes motion reports of a pointing device
+pub struct Pointing {
+    sin: I16F16,
+    cos: I16F16,
+    snap: Option<I16F16>,
+    precision_divisor: I16F16,
+    precision: bool,
+    smoothing: Option<(Ema<i16>, Ema<i16>)>,
+    remainder: (I16F16, I16F16),
+}
+
+impl Pointing {
+    /// Creates a new processor
+    ///
+    /// # Panics
+    /// This function panics if the snap angle is 45 degrees or more, the precision divisor is 0,
+    /// or the smoothing is 16 or more.
+    pub fn new(config: &PointingConfig) -> Self {
+        assert!(
+            config.snap_angle < 45,
+            "snap angle must be below 45 degrees"
+        );
+        assert!(
+            config.precision_divisor > 0,
+            "precision divisor must not be 0"
+        );
+        // With the Y axis pointing down, a positive angle rotates the motion clockwise, which
+        // undoes the rotation of the sensor
+        let angle = fixed::degrees(i32::from(config.rotation));
+        let snap = (config.snap_angle > 0).then(|| {
+            let angle = fixed::degrees(i32::from(config.snap_angle));
+            fixed::sin(angle) / fixed::cos(angle)
+        });
+        let smoothing = (config.smoothing > 0).then(|| {
+            (
+                Ema::<i16>::new(config.smoothing),
+                Ema::<i16>::new(config.smoothing),
+            )
+        });
+        Self {
+            sin: fixed::sin(angle),
+            cos: fixed::cos(angle),
+            snap,
+            precision_divisor: I16F16::from_int(i32::from(config.precision_divisor)),
+            precision: false,
+            smoothing,
+            remainder: (I16F16::ZERO, I16F16::ZERO),
+        }
+    }
+    /// Enables or disables precision mode
+    pub fn set_precision(&mut self, precision: bool) {
+        self.precision = precision;
+    }
+    /// Returns `true` if precision mode is enabled
+    pub fn precision(&self) -> bool {
+        self.precision
+    }
+    /// Processes a motion report from the sensor
+    ///
+    /// Returns the motion to report to the host.
+    pub fn process(&mut self, dx: i16, dy: i16) -> (i16, i16) {
+        let (dx, dy) = match &mut self.smoothing {
+            Some((x, y)) => (x.update(dx), y.update(dy)),
+            None => (dx, dy),
+        };
+        let (dx, dy) = (
+            I16F16::from_int(i32::from(dx)),
+            I16F16::from_int(i32::from(dy)),
+        );
+        let mut x = dx * self.cos - dy * self.sin;
+        let mut y = dx * self.sin + dy * self.cos;
+        if let Some(tan) = self.snap {
+            if y.abs() <= x.abs() * tan {
+                y = I16F16::ZERO;
+            } else if x.abs() <= y.abs() * tan {
+                x = I16F16::ZERO;
+            }
+        }
+        if self.precision {
+            x = x / self.precision_divisor;
+            y = y / self.precision_divisor;
+        }
+        let x = Self::carry(x, &mut self.remainder.0);
+        let y = Self::carry(y, &mut self.remainder.1);
+        (x, y)
+    }
+    /// Discards the sub-count motion carried over from previous reports
+    pub fn reset(&mut self) {
+        self.remainder = (I16F16::ZERO, I16F16::ZERO);
+        if let Some((x, y)) = &mut self.smoothing {
+            x.reset();
+            y.reset();
+        }
+    }
+    fn carry(value: I16F16, remainder: &mut I16F16) -> i16 {
+        let total = value + *remainder;
+        let whole = total.floor().clamp(i16::MIN.into(), i16::MAX.into());
+        *remainder = total - I16F16::from_int(whole);
+        whole as i16
+    }
+}
@@ -0,0 +1,86 @@
+//! Random number generation
+//!
+//! Effects and jitter only need cheap, non-cryptographic randomness, so the default source is a
+//! xorshift generator seeded from the jitter of the ring oscillator. Chips with a hardware RNG
+//! can provide their own [`RandomSource`].
+
+use rp_pico::hal::pac;
+
+/// A source of random numbers
+pub trait RandomSource {
+    /// Returns the next random number
+    fn next_u32(&mut self) -> u32;
+    /// Returns a random number in `0..bound`
+    ///
+    /// # Panics
+    /// This function panics if `bound` is 0.
+    fn next_below(&mut self, bound: u32) -> u32 {
+        assert!(bound != 0, "bound must not be 0");
+        // Multiply-shift range reduction, avoiding a division
+        ((u64::from(self.next_u32()) * u64::from(bound)) >> 32) as u32
+    }
+    /// Returns a random byte
+    fn next_u8(&mut self) -> u8 {
+        (self.next_u32() >> 24) as u8
+    }
+    /// Returns `true` with a probability of `numerator / 256`
+    fn chance(&mut self, numerator: u8) -> bool {
+        self.next_u8() < numerator
+    }
+}
+
+/// 32 bit xorshift generator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xorshift32 {
+    state: u32,
+}
+
+impl Xorshift32 {
+    /// Creates a new generator
+    ///
+    /// The seed is scrambled first, so similar seeds produce unrelated sequences.
+    pub const fn new(seed: u32) -> Self {
+        let state = mix(seed);
+        Self {
+            state: if state == 0 { 0x9E37_79B9 } else { state },
+        }
+    }
+}
+
+impl RandomSource for Xorshift32 {
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+}
+
+/// Finalizer of MurmurHash3, used to spread the entropy of a seed over all bits
+const fn mix(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xC2B2_AE35);
+    x ^= x >> 16;
+    x
+}
+
+/// Collects a seed from the random bit of the ring oscillator
+///
+/// The random bit is sampled from a free-running oscillator and is not uniformly distributed,
+/// so several samples are folded into every seed bit. The ring oscillator must be running,
+/// which it is unless it was explicitly disabled.
+pub fn rosc_seed(rosc: &pac::ROSC) -> u32 {
+    let mut seed = 0u32;
+    for _ in 0..32 {
+        let mut bit = false;
+        for _ in 0..8 {
+            bit ^= rosc.randombit.read().randombit().bit();
+        }
+        seed = (seed << 1) | u32::from(bit);
+    }
+    seed
+}
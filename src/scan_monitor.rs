@@ -0,0 +1,100 @@
+//! Scan loop timing monitor
+//!
+//! Records how regular the scan loop runs, so features that slow it down show up in numbers
+//! rather than as an occasional laggy key. The Cortex-M0+ has no DWT cycle counter, so the
+//! monitor works on the microsecond timer instead.
+
+/// Number of jitter histogram buckets
+pub const BUCKETS: usize = 12;
+
+/// Statistics collected by a [`ScanMonitor`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct ScanStats {
+    /// Number of measured scan periods
+    pub scans: u32,
+    /// Shortest scan period in microseconds
+    pub min_period: u32,
+    /// Longest scan period in microseconds
+    pub max_period: u32,
+    /// Number of scan periods longer than the deadline
+    pub missed: u32,
+    /// Histogram of the deviation from the target period
+    ///
+    /// Bucket 0 counts exact periods, bucket `n` deviations of `2^(n-1)` up to `2^n - 1`
+    /// microseconds. The last bucket also counts all larger deviations.
+    pub histogram: [u32; BUCKETS],
+}
+
+/// Monitors the period of the scan loop
+pub struct ScanMonitor {
+    period: u32,
+    deadline: u32,
+    panic_on_miss: bool,
+    last: Option<u32>,
+    stats: ScanStats,
+}
+
+impl ScanMonitor {
+    /// Creates a new monitor for a loop that should run every `period` microseconds and must not
+    /// take longer than `deadline` microseconds
+    pub const fn new(period: u32, deadline: u32) -> Self {
+        Self {
+            period,
+            deadline,
+            panic_on_miss: false,
+            last: None,
+            stats: ScanStats {
+                scans: 0,
+                min_period: u32::MAX,
+                max_period: 0,
+                missed: 0,
+                histogram: [0; BUCKETS],
+            },
+        }
+    }
+    /// Makes the monitor panic when the deadline is missed
+    ///
+    /// Meant for catching regressions during development.
+    pub const fn panic_on_miss(mut self, panic_on_miss: bool) -> Self {
+        self.panic_on_miss = panic_on_miss;
+        self
+    }
+    /// Records the start of a scan and returns the period since the previous one
+    ///
+    /// `now` is a free-running microsecond timestamp.
+    ///
+    /// # Panics
+    /// This function panics if the deadline was missed and the monitor was configured to panic.
+    pub fn record(&mut self, now: u32) -> Option<u32> {
+        let last = self.last.replace(now)?;
+        let period = now.wrapping_sub(last);
+        let stats = &mut self.stats;
+        stats.scans = stats.scans.saturating_add(1);
+        stats.min_period = stats.min_period.min(period);
+        stats.max_period = stats.max_period.max(period);
+        let jitter = if period > self.period {
+            period - self.period
+        } else {
+            self.period - period
+        };
+        let bucket = ((u32::BITS - jitter.leading_zeros()) as usize).min(BUCKETS - 1);
+        stats.histogram[bucket] = stats.histogram[bucket].saturating_add(1);
+        if period > self.deadline {
+            stats.missed = stats.missed.saturating_add(1);
+            if self.panic_on_miss {
+                panic!("scan deadline missed: {}us > {}us", period, self.deadline);
+            }
+        }
+        Some(period)
+    }
+    /// Returns the statistics collected so far
+    pub fn stats(&self) -> &ScanStats {
+        &self.stats
+    }
+    /// Clears the statistics
+    ///
+    /// The next call to [`record`](Self::record) starts a new measurement.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.period, self.deadline).panic_on_miss(self.panic_on_miss);
+    }
+}
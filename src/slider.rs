@@ -0,0 +1,228 @@
+//! Analog sliders and faders
+//!
+//! Converts raw ADC samples of a potentiometer into a stable output value, e.g. a MIDI CC value
+//! (0-127) or an LED brightness (0-255), and changes of that value into the report for the
+//! [`Target`] the slider controls.
+
+use crate::filter::Ema;
+
+/// HID consumer usage Display Brightness Increment
+pub const BRIGHTNESS_INCREMENT: u16 = 0x006F;
+/// HID consumer usage Display Brightness Decrement
+pub const BRIGHTNESS_DECREMENT: u16 = 0x0070;
+/// HID consumer usage Volume Increment
+pub const VOLUME_INCREMENT: u16 = 0x00E9;
+/// HID consumer usage Volume Decrement
+pub const VOLUME_DECREMENT: u16 = 0x00EA;
+/// HID consumer usage AC Pan
+pub const AC_PAN: u16 = 0x0238;
+
+/// How the slider takes over a value that was changed by other means
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Takeover {
+    /// The value jumps to the slider position as soon as the slider moves
+    Jump,
+    /// The slider has no effect until it passes the current value
+    Pickup,
+}
+
+/// What a slider controls
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Target {
+    /// Host volume, changed in consumer Volume Increment and Decrement steps
+    Volume,
+    /// Display brightness, changed in consumer Display Brightness Increment and Decrement steps
+    Brightness,
+    /// Stereo balance, set with the consumer AC Pan control
+    Pan,
+    /// A MIDI control change
+    MidiCc {
+        /// MIDI channel, from 0 to 15
+        channel: u8,
+        /// Controller number, from 0 to 119
+        controller: u8,
+    },
+}
+
+/// Report to send for a change of the slider output
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Report {
+    /// Press and release a consumer usage `count` times
+    ConsumerSteps { usage: u16, count: u8 },
+    /// Set a consumer linear control to a value from -127 to 127
+    ConsumerValue { usage: u16, value: i8 },
+    /// Send a MIDI message
+    Midi([u8; 3]),
+}
+
+/// Slider parameters
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct SliderConfig {
+    /// Largest raw sample the ADC produces
+    pub raw_max: u16,
+    /// Raw range at either end of the travel that maps to the minimum and maximum output
+    pub dead_zone: u16,
+    /// Largest output value
+    pub output_max: u8,
+    /// Shift of the smoothing filter; higher values are smoother but slower
+    pub filter_shift: u8,
+    /// Takeover mode
+    pub takeover: Takeover,
+    /// What the slider controls
+    pub target: Target,
+}
+
+impl Default for SliderConfig {
+    fn default() -> Self {
+        Self {
+            raw_max: 4095,
+            dead_zone: 64,
+            output_max: 127,
+            filter_shift: 3,
+            takeover: Takeover::Jump,
+            target: Target::MidiCc {
+                channel: 0,
+                controller: 7,
+            },
+        }
+    }
+}
+
+/// A change of the slider output
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct SliderChange {
+    /// New output value
+    pub value: u8,
+    /// Difference to the previous output value, for relative controls like volume
+    pub delta: i16,
+}
+
+/// An analog slider
+pub struct Slider {
+    config: SliderConfig,
+    /// Filter of the raw value, in 1/256 ADC counts
+    filter: Ema<u32>,
+    /// Output value matching the slider position
+    position: Option<u8>,
+    value: u8,
+    /// `false` until the slider passes the current value in pickup mode
+    engaged: bool,
+}
+
+impl Slider {
+    /// Creates a new slider with an output value of 0
+    ///
+    /// # Panics
+    /// This function panics if the filter shift is 32 or more, or the MIDI channel or
+    /// controller of the target is out of range.
+    pub fn new(config: SliderConfig) -> Self {
+        if let Target::MidiCc {
+            channel,
+            controller,
+        } = config.target
+        {
+            assert!(channel < 16, "MIDI channel out of range");
+            assert!(controller < 120, "MIDI controller out of range");
+        }
+        Self {
+            config,
+            filter: Ema::<u32>::new(config.filter_shift),
+            position: None,
+            value: 0,
+            engaged: config.takeover == Takeover::Jump,
+        }
+    }
+    /// Current output value
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+    /// Sets the output value from another source (e.g. a keycode or the host)
+    ///
+    /// In pickup mode the slider has to pass the new value before it takes over again.
+    pub fn set_value(&mut self, value: u8) {
+        self.value = value.min(self.config.output_max);
+        self.engaged = self.config.takeover == Takeover::Jump;
+    }
+    /// Raw range (in ADC counts) between the dead zones
+    fn range(&self) -> (u32, u32) {
+        let low = u32::from(self.config.dead_zone);
+        let high = u32::from(self.config.raw_max.saturating_sub(self.config.dead_zone));
+        (low, high.max(low + 1))
+    }
+    /// Maps a filtered raw value (in ADC counts) to an output value
+    fn map(&self, raw: u32) -> u8 {
+        let (low, high) = self.range();
+        let output_max = u32::from(self.config.output_max);
+        let raw = raw.clamp(low, high) - low;
+        ((raw * output_max + (high - low) / 2) / (high - low)) as u8
+    }
+    /// Filtered raw value (in 1/256 ADC counts) at the center of an output value
+    fn map_inverse(&self, value: u8) -> u32 {
+        let (low, high) = self.range();
+        let output_max = u32::from(self.config.output_max.max(1));
+        (low + (high - low) * u32::from(value) / output_max) << 8
+    }
+    /// Feeds a raw ADC sample into the slider
+    ///
+    /// Returns the new output value if it changed.
+    pub fn update(&mut self, sample: u16) -> Option<SliderChange> {
+        let filtered = self.filter.update(u32::from(sample) << 8);
+        let position = self.map(filtered >> 8);
+        let last = self.position.replace(position);
+        if !self.engaged {
+            // Take over once the slider reaches or passes the current value
+            match last {
+                Some(last)
+                    if position == self.value || (last < self.value) != (position < self.value) =>
+                {
+                    self.engaged = true
+                }
+                _ => return None,
+            }
+        }
+        if position == self.value {
+            return None;
+        }
+        // Hysteresis: ignore the output flickering between two neighbouring values
+        if self.value.abs_diff(position) == 1 {
+            let (low, high) = self.range();
+            let step = ((high - low) << 8) / u32::from(self.config.output_max.max(1));
+            if filtered.abs_diff(self.map_inverse(position)) > step / 4 {
+                return None;
+            }
+        }
+        let delta = i16::from(position) - i16::from(self.value);
+        self.value = position;
+        Some(SliderChange {
+            value: position,
+            delta,
+        })
+    }
+    /// Returns the report that applies `change` to the target of the slider
+    pub fn report(&self, change: &SliderChange) -> Report {
+        let steps = |increment, decrement| Report::ConsumerSteps {
+            usage: if change.delta > 0 {
+                increment
+            } else {
+                decrement
+            },
+            count: change.delta.unsigned_abs().min(255) as u8,
+        };
+        match self.config.target {
+            Target::Volume => steps(VOLUME_INCREMENT, VOLUME_DECREMENT),
+            Target::Brightness => steps(BRIGHTNESS_INCREMENT, BRIGHTNESS_DECREMENT),
+            Target::Pan => {
+                let output_max = i32::from(self.config.output_max.max(1));
+                let value = (2 * i32::from(change.value) - output_max) * 127 / output_max;
+                Report::ConsumerValue {
+                    usage: AC_PAN,
+                    value: value as i8,
+                }
+            }
+            Target::MidiCc {
+                channel,
+                controller,
+            } => Report::Midi([0xB0 | channel, controller, change.value.min(127)]),
+        }
+    }
+}
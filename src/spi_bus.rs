@@ -0,0 +1,549 @@
+//! Shared SPI bus
+//!
+//! Lets several drivers (display, pointing sensor, LEDs) share one SPI peripheral. Every driver
+//! gets its own [`SpiDevice`] handle implementing the blocking SPI traits, which asserts the
+//! device's chip select around each transfer so the other devices ignore it. Each device has
+//! its own [`SpiConfig`], and the peripheral is switched to it at the start of every
+//! transaction.
+//!
+//! Large writes, like display updates, can be queued with [`SpiDevice::queue_write`] instead.
+//! They are sent with DMA one after another, and [`SpiBus::poll`], called from the scan loop or
+//! the DMA interrupt, starts the next one once the previous one is done. Blocking transactions
+//! of other devices run in between, so a pointing sensor read waits for at most one queued
+//! transfer.
+//!
+//! The peripheral is handed out through a [`BusMutex`], so a bus in a `static` can be used from
+//! interrupt handlers or the other core. A transaction that finds the bus taken fails with
+//! [`Error::Busy`].
+//!
+//! APA102 LEDs have no chip select input. On a shared bus, their clock has to be gated, e.g. with
+//! the output enable of a level shifter, which is then used as their chip select.
+
+use core::{convert::Infallible, fmt};
+
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+    spi::{Mode, Phase, Polarity},
+};
+use rp_pico::hal::{
+    pac,
+    spi::{Enabled, Spi, SpiDevice as HalSpiDevice},
+};
+
+use crate::bus::{BusMutex, Busy};
+
+/// Mode and clock of a device
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct SpiConfig {
+    /// Clock polarity and phase
+    pub mode: Mode,
+    /// Clock frequency in Hz
+    pub frequency: u32,
+}
+
+impl SpiConfig {
+    /// Returns the number of the mode, from 0 to 3
+    pub fn mode_number(&self) -> u8 {
+        u8::from(self.mode.polarity == Polarity::IdleHigh) << 1
+            | u8::from(self.mode.phase == Phase::CaptureOnSecondTransition)
+    }
+}
+
+// `Mode` implements neither `Debug` nor `Format`, so both print the mode number instead
+impl fmt::Debug for SpiConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiConfig")
+            .field("mode", &self.mode_number())
+            .field("frequency", &self.frequency)
+            .finish()
+    }
+}
+
+impl defmt::Format for SpiConfig {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "SpiConfig {{ mode: {}, frequency: {} }}",
+            self.mode_number(),
+            self.frequency
+        );
+    }
+}
+
+/// Errors returned by a device on a shared bus
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Error<E> {
+    /// The bus is used by someone else
+    Busy,
+    /// The SPI transfer failed
+    Spi(E),
+}
+
+impl<E> From<Busy> for Error<E> {
+    fn from(_: Busy) -> Self {
+        Self::Busy
+    }
+}
+
+/// Errors returned when queueing a transfer
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum QueueError {
+    /// The bus is used by someone else
+    Busy,
+    /// The queue is full
+    Full,
+}
+
+impl From<Busy> for QueueError {
+    fn from(_: Busy) -> Self {
+        Self::Busy
+    }
+}
+
+/// An SPI peripheral whose mode and clock can be changed between transfers
+///
+/// The RP2040 HAL only sets the mode when the peripheral is initialized, so [`Rp2040Spi`]
+/// implements this by writing the clock and format registers.
+pub trait Configure {
+    /// Switches the peripheral to the given mode and clock
+    fn configure(&mut self, config: &SpiConfig);
+}
+
+/// An SPI peripheral that can send a buffer in the background
+pub trait DmaWrite {
+    /// Starts sending `data`, discarding the received bytes
+    fn start_write(&mut self, data: &'static [u8]);
+    /// Returns `true` once the last write has been sent completely
+    fn is_write_done(&mut self) -> bool;
+}
+
+/// Chip select lines of the devices on a bus, numbered from 0
+///
+/// Implemented for arrays of pins, and for tuples of up to four pins of different types.
+pub trait ChipSelect {
+    /// Number of devices
+    const COUNT: usize;
+    /// Selects (drives low) or deselects the chip select of `device`
+    fn set(&mut self, device: usize, selected: bool);
+}
+
+fn drive<P: OutputPin<Error = Infallible>>(pin: &mut P, selected: bool) {
+    if selected {
+        pin.set_low().unwrap();
+    } else {
+        pin.set_high().unwrap();
+    }
+}
+
+impl<P: OutputPin<Error = Infallible>, const N: usize> ChipSelect for [P; N] {
+    const COUNT: usize = N;
+    fn set(&mut self, device: usize, selected: bool) {
+        drive(&mut self[device], selected);
+    }
+}
+
+macro_rules! chip_select_tuple {
+    ($($count:literal => ($($pin:ident . $index:tt),+);)*) => {
+        $(
+            impl<$($pin: OutputPin<Error = Infallible>),+> ChipSelect for ($($pin,)+) {
+                const COUNT: usize = $count;
+                fn set(&mut self, device: usize, selected: bool) {
+                    match device {
+                        $($index => drive(&mut self.$index, selected),)+
+                        _ => panic!("no such device"),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+chip_select_tuple! {
+    1 => (A.0);
+    2 => (A.0, B.1);
+    3 => (A.0, B.1, C.2);
+    4 => (A.0, B.1, C.2, D.3);
+}
+
+/// A write waiting for the bus
+#[derive(Copy, Clone)]
+struct Queued {
+    device: usize,
+    config: SpiConfig,
+    data: &'static [u8],
+}
+
+/// Peripheral, chip selects and queued transfers of a bus
+struct Inner<SPI, CS, const N: usize> {
+    spi: SPI,
+    cs: CS,
+    /// Configuration the peripheral was last switched to
+    config: Option<SpiConfig>,
+    /// Device whose queued write is running
+    active: Option<usize>,
+    queue: [Option<Queued>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<SPI: Configure + DmaWrite, CS: ChipSelect, const N: usize> Inner<SPI, CS, N> {
+    fn switch(&mut self, config: &SpiConfig) {
+        if self.config.as_ref() != Some(config) {
+            self.spi.configure(config);
+            self.config = Some(*config);
+        }
+    }
+    fn push(&mut self, transfer: Queued) -> Result<(), QueueError> {
+        if self.len == N {
+            return Err(QueueError::Full);
+        }
+        self.queue[(self.head + self.len) % N] = Some(transfer);
+        self.len += 1;
+        Ok(())
+    }
+    fn pop(&mut self) -> Option<Queued> {
+        if self.len == 0 {
+            return None;
+        }
+        let transfer = self.queue[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        transfer
+    }
+    fn is_queued(&self, device: usize) -> bool {
+        (0..self.len)
+            .any(|i| matches!(self.queue[(self.head + i) % N], Some(q) if q.device == device))
+    }
+    /// Deselects the device of the running write if it is done
+    ///
+    /// Returns `true` if no write is running anymore.
+    fn finish(&mut self) -> bool {
+        match self.active {
+            Some(device) if self.spi.is_write_done() => {
+                self.cs.set(device, false);
+                self.active = None;
+                true
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+    /// Finishes the running write and starts the next queued one
+    fn poll(&mut self) {
+        if !self.finish() {
+            return;
+        }
+        if let Some(next) = self.pop() {
+            self.switch(&next.config);
+            self.cs.set(next.device, true);
+            self.spi.start_write(next.data);
+            self.active = Some(next.device);
+        }
+    }
+    /// Waits until `device` can use the bus without overtaking its own queued writes
+    fn settle(&mut self, device: usize) {
+        while !self.finish() {}
+        if self.is_queued(device) {
+            while self.active.is_some() || self.len != 0 {
+                self.poll();
+            }
+        }
+    }
+}
+
+/// An SPI peripheral shared by the devices selected by `CS`, queueing up to `N` writes
+pub struct SpiBus<SPI, CS, const N: usize = 4> {
+    inner: BusMutex<Inner<SPI, CS, N>>,
+}
+
+impl<SPI: Configure + DmaWrite, CS: ChipSelect, const N: usize> SpiBus<SPI, CS, N> {
+    /// Creates a new shared bus and deselects all devices
+    pub fn new(spi: SPI, mut cs: CS) -> Self {
+        for device in 0..CS::COUNT {
+            cs.set(device, false);
+        }
+        Self {
+            inner: BusMutex::new(Inner {
+                spi,
+                cs,
+                config: None,
+                active: None,
+                queue: [None; N],
+                head: 0,
+                len: 0,
+            }),
+        }
+    }
+    /// Creates a handle for device number `device`, which uses `config` for its transfers
+    ///
+    /// # Panics
+    /// This function panics if there is no chip select for `device`.
+    pub fn device(&self, device: usize, config: SpiConfig) -> SpiDevice<'_, SPI, CS, N> {
+        assert!(device < CS::COUNT, "no such device");
+        SpiDevice {
+            bus: self,
+            device,
+            config,
+        }
+    }
+    /// Starts the next queued write once the running one is done
+    ///
+    /// Call this regularly, e.g. from the scan loop or the DMA interrupt.
+    ///
+    /// # Errors
+    /// This function returns [`Busy`] if the bus is used by someone else.
+    pub fn poll(&self) -> Result<(), Busy> {
+        self.inner.try_lock()?.poll();
+        Ok(())
+    }
+    /// Waits for the queued writes and releases the peripheral and the chip selects
+    pub fn free(mut self) -> (SPI, CS) {
+        let inner = self.inner.get_mut();
+        while inner.active.is_some() || inner.len != 0 {
+            inner.poll();
+        }
+        let inner = self.inner.into_inner();
+        (inner.spi, inner.cs)
+    }
+}
+
+/// Handle for a device on a shared bus
+pub struct SpiDevice<'a, SPI, CS, const N: usize> {
+    bus: &'a SpiBus<SPI, CS, N>,
+    device: usize,
+    config: SpiConfig,
+}
+
+impl<SPI: Configure + DmaWrite, CS: ChipSelect, const N: usize> SpiDevice<'_, SPI, CS, N> {
+    /// Returns the mode and clock of the device
+    pub fn config(&self) -> &SpiConfig {
+        &self.config
+    }
+    /// Changes the mode and clock of the device, taking effect with the next transaction
+    pub fn set_config(&mut self, config: SpiConfig) {
+        self.config = config;
+    }
+    /// Runs `f` with the device selected and exclusive access to the peripheral
+    ///
+    /// The running queued write is finished first, and if the device has writes queued itself,
+    /// they are all sent before, so its transfers stay in order. The peripheral is then switched
+    /// to the configuration of the device. Use this to keep the device selected over several
+    /// transfers.
+    ///
+    /// # Errors
+    /// This function returns [`Busy`] if the bus is used by someone else, including a caller
+    /// of `f`.
+    pub fn transaction<R>(&mut self, f: impl FnOnce(&mut SPI) -> R) -> Result<R, Busy> {
+        let mut inner = self.bus.inner.try_lock()?;
+        inner.settle(self.device);
+        inner.switch(&self.config);
+        inner.cs.set(self.device, true);
+        let result = f(&mut inner.spi);
+        inner.cs.set(self.device, false);
+        Ok(result)
+    }
+    /// Queues `data` to be written with DMA, with the device selected
+    ///
+    /// The write starts right away if the bus is idle, otherwise [`SpiBus::poll`] starts it
+    /// after the writes queued before it.
+    ///
+    /// # Errors
+    /// This function returns an error if the bus is used by someone else or the queue is full.
+    pub fn queue_write(&self, data: &'static [u8]) -> Result<(), QueueError> {
+        let mut inner = self.bus.inner.try_lock()?;
+        inner.push(Queued {
+            device: self.device,
+            config: self.config,
+            data,
+        })?;
+        inner.poll();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, const N: usize> Write<u8> for SpiDevice<'_, SPI, CS, N>
+where
+    SPI: Configure + DmaWrite + Write<u8>,
+    CS: ChipSelect,
+{
+    type Error = Error<SPI::Error>;
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.transaction(|spi| spi.write(words))?
+            .map_err(Error::Spi)
+    }
+}
+
+impl<SPI, CS, const N: usize> Transfer<u8> for SpiDevice<'_, SPI, CS, N>
+where
+    SPI: Configure + DmaWrite + Transfer<u8>,
+    CS: ChipSelect,
+{
+    type Error = Error<SPI::Error>;
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.transaction(|spi| spi.transfer(words))?
+            .map_err(Error::Spi)
+    }
+}
+
+/// SPI peripheral of the RP2040, with its registers and transmit DMA request
+pub trait Rp2040SpiDevice: HalSpiDevice {
+    /// DMA request signalling that the transmit FIFO has room
+    const TX_DREQ: u32;
+    /// Returns the registers of the peripheral
+    fn registers() -> &'static pac::spi0::RegisterBlock;
+}
+
+impl Rp2040SpiDevice for pac::SPI0 {
+    const TX_DREQ: u32 = 16;
+    fn registers() -> &'static pac::spi0::RegisterBlock {
+        // SAFETY: the registers are always mapped
+        unsafe { &*pac::SPI0::ptr() }
+    }
+}
+
+impl Rp2040SpiDevice for pac::SPI1 {
+    const TX_DREQ: u32 = 18;
+    fn registers() -> &'static pac::spi0::RegisterBlock {
+        // SAFETY: the registers are always mapped
+        unsafe { &*pac::SPI1::ptr() }
+    }
+}
+
+/// Base address of the DMA channel registers, 0x40 bytes per channel
+const DMA_BASE: usize = 0x5000_0000;
+const DMA_READ_ADDR: usize = 0x00;
+const DMA_WRITE_ADDR: usize = 0x04;
+const DMA_TRANS_COUNT: usize = 0x08;
+const DMA_CTRL_TRIG: usize = 0x0C;
+const DMA_EN: u32 = 1 << 0;
+const DMA_INCR_READ: u32 = 1 << 4;
+const DMA_CHAIN_TO_SHIFT: u32 = 11;
+const DMA_TREQ_SEL_SHIFT: u32 = 15;
+const DMA_BUSY: u32 = 1 << 24;
+/// Number of DMA channels
+const DMA_CHANNELS: u8 = 12;
+
+/// SPI peripheral of the RP2040 that can be reconfigured and sends queued writes with DMA
+pub struct Rp2040Spi<D: Rp2040SpiDevice> {
+    spi: Spi<Enabled, D, 8>,
+    peri_clk_hz: u32,
+    channel: u8,
+}
+
+impl<D: Rp2040SpiDevice> Rp2040Spi<D> {
+    /// Wraps an initialized 8 bit SPI peripheral clocked with `peri_clk_hz`, using DMA channel
+    /// `channel` for queued writes
+    ///
+    /// # Panics
+    /// This function panics if `channel` is not a DMA channel.
+    ///
+    /// # Safety
+    /// Nothing else may use DMA channel `channel` while the peripheral exists.
+    pub unsafe fn new(spi: Spi<Enabled, D, 8>, peri_clk_hz: u32, channel: u8) -> Self {
+        assert!(channel < DMA_CHANNELS, "not a DMA channel");
+        Self {
+            spi,
+            peri_clk_hz,
+            channel,
+        }
+    }
+    /// Releases the peripheral
+    pub fn free(self) -> Spi<Enabled, D, 8> {
+        self.spi
+    }
+    fn channel_register(&self, offset: usize) -> *mut u32 {
+        (DMA_BASE + 0x40 * usize::from(self.channel) + offset) as *mut u32
+    }
+}
+
+impl<D: Rp2040SpiDevice> Configure for Rp2040Spi<D> {
+    fn configure(&mut self, config: &SpiConfig) {
+        let regs = D::registers();
+        while regs.sspsr.read().bsy().bit_is_set() {}
+        // The clock is peri_clk / (prescale * (1 + postdiv)), with an even prescale from 2 to
+        // 254. Use the smallest prescale that leaves postdiv in range, then the largest
+        // postdiv that does not exceed the requested frequency.
+        let frequency = config.frequency.max(1);
+        let prescale = (2..=254)
+            .step_by(2)
+            .find(|prescale| {
+                u64::from(self.peri_clk_hz) < u64::from(prescale + 2) * 256 * u64::from(frequency)
+            })
+            .unwrap_or(254);
+        let postdiv = (1..=255)
+            .rev()
+            .find(|postdiv| self.peri_clk_hz / (prescale * postdiv) > frequency)
+            .unwrap_or(0);
+        regs.sspcr1.modify(|_, w| w.sse().clear_bit());
+        // SAFETY: prescale and postdiv are in the range of their fields
+        regs.sspcpsr
+            .write(|w| unsafe { w.cpsdvsr().bits(prescale as u8) });
+        regs.sspcr0.modify(|_, w| unsafe {
+            w.scr()
+                .bits(postdiv as u8)
+                .spo()
+                .bit(config.mode.polarity == Polarity::IdleHigh)
+                .sph()
+                .bit(config.mode.phase == Phase::CaptureOnSecondTransition)
+        });
+        regs.sspcr1.modify(|_, w| w.sse().set_bit());
+    }
+}
+
+impl<D: Rp2040SpiDevice> DmaWrite for Rp2040Spi<D> {
+    fn start_write(&mut self, data: &'static [u8]) {
+        let regs = D::registers();
+        regs.sspdmacr.modify(|_, w| w.txdmae().set_bit());
+        let ctrl = DMA_EN
+            | DMA_INCR_READ
+            | u32::from(self.channel) << DMA_CHAIN_TO_SHIFT
+            | D::TX_DREQ << DMA_TREQ_SEL_SHIFT;
+        // SAFETY: the channel belongs to the peripheral, and `data` outlives the transfer.
+        // Chaining to itself disables chaining, and bytes are the default transfer size.
+        unsafe {
+            self.channel_register(DMA_READ_ADDR)
+                .write_volatile(data.as_ptr() as u32);
+            self.channel_register(DMA_WRITE_ADDR)
+                .write_volatile(&regs.sspdr as *const _ as u32);
+            self.channel_register(DMA_TRANS_COUNT)
+                .write_volatile(data.len() as u32);
+            self.channel_register(DMA_CTRL_TRIG).write_volatile(ctrl);
+        }
+    }
+    fn is_write_done(&mut self) -> bool {
+        let regs = D::registers();
+        // SAFETY: the channel belongs to the peripheral
+        let ctrl = unsafe { self.channel_register(DMA_CTRL_TRIG).read_volatile() };
+        if ctrl & DMA_BUSY != 0 || regs.sspsr.read().bsy().bit_is_set() {
+            return false;
+        }
+        // The received bytes were not read, so drain the receive FIFO and clear the overrun
+        while regs.sspsr.read().rne().bit_is_set() {
+            regs.sspdr.read();
+        }
+        regs.sspicr.write(|w| w.roric().set_bit());
+        regs.sspdmacr.modify(|_, w| w.txdmae().clear_bit());
+        true
+    }
+}
+
+impl<D: Rp2040SpiDevice> Write<u8> for Rp2040Spi<D>
+where
+    Spi<Enabled, D, 8>: Write<u8>,
+{
+    type Error = <Spi<Enabled, D, 8> as Write<u8>>::Error;
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.spi.write(words)
+    }
+}
+
+impl<D: Rp2040SpiDevice> Transfer<u8> for Rp2040Spi<D>
+where
+    Spi<Enabled, D, 8>: Transfer<u8>,
+{
+    type Error = <Spi<Enabled, D, 8> as Transfer<u8>>::Error;
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.spi.transfer(words)
+    }
+}
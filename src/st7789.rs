@@ -0,0 +1,266 @@
+//! ST7789 SPI LCD driver
+//!
+//! Drives the panel in 16 bit RGB565 mode. Images are stored run-length encoded in flash (see
+//! [`Image`]) and streamed to the panel without a framebuffer. The build script encodes the
+//! images in `assets`, see [`crate::assets`].
+//!
+//! Paging between status, animation and settings screens is not implemented yet. The status
+//! and settings screens need text rendering, which this driver does not provide, so the screen
+//! manager is left for a separate module on top of [`St7789`].
+//!
+//! Images are referenced with plain `&'static [u8]` instead of tiny pointers into a flash pool.
+//! The offset of an image in flash is only known after linking, so the `const` images in
+//! [`crate::assets`] cannot store it.
+
+use core::convert::Infallible;
+
+use embedded_hal::{
+    blocking::{delay::DelayMs, spi::Write},
+    digital::v2::OutputPin,
+};
+
+const SWRESET: u8 = 0x01;
+const SLPOUT: u8 = 0x11;
+const NORON: u8 = 0x13;
+const INVON: u8 = 0x21;
+const DISPON: u8 = 0x29;
+const CASET: u8 = 0x2A;
+const RASET: u8 = 0x2B;
+const RAMWR: u8 = 0x2C;
+const MADCTL: u8 = 0x36;
+const COLMOD: u8 = 0x3A;
+
+/// Number of pixels sent per SPI transfer
+const CHUNK: usize = 32;
+
+/// Converts a 24 bit color to RGB565
+pub const fn rgb565(r: u8, g: u8, b: u8) -> u16 {
+    ((r as u16 & 0xF8) << 8) | ((g as u16 & 0xFC) << 3) | (b as u16 >> 3)
+}
+
+/// Panel orientation
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+    PortraitFlipped,
+    LandscapeFlipped,
+}
+
+impl Orientation {
+    const fn madctl(self) -> u8 {
+        match self {
+            Orientation::Portrait => 0x00,
+            Orientation::Landscape => 0x60,
+            Orientation::PortraitFlipped => 0xC0,
+            Orientation::LandscapeFlipped => 0xA0,
+        }
+    }
+}
+
+/// Panel geometry
+///
+/// Most panels do not use the full 240×320 RAM of the controller, so the visible area starts
+/// at an offset.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Panel {
+    pub width: u16,
+    pub height: u16,
+    pub x_offset: u16,
+    pub y_offset: u16,
+    pub orientation: Orientation,
+}
+
+/// Errors returned by the display
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Error<E> {
+    /// The SPI transfer failed
+    Spi(E),
+    /// The area is not inside the panel
+    OutOfBounds,
+}
+
+/// ST7789 display
+pub struct St7789<SPI, DC> {
+    spi: SPI,
+    dc: DC,
+    panel: Panel,
+}
+
+impl<SPI, DC> St7789<SPI, DC>
+where
+    SPI: Write<u8>,
+    DC: OutputPin<Error = Infallible>,
+{
+    /// Creates a new display and initializes the panel
+    ///
+    /// # Errors
+    /// This function returns an error if the SPI transfer fails.
+    pub fn new(
+        spi: SPI,
+        dc: DC,
+        panel: Panel,
+        delay: &mut impl DelayMs<u8>,
+    ) -> Result<Self, Error<SPI::Error>> {
+        let mut this = Self { spi, dc, panel };
+        this.command(SWRESET, &[])?;
+        delay.delay_ms(150);
+        this.command(SLPOUT, &[])?;
+        delay.delay_ms(10);
+        this.command(COLMOD, &[0x55])?;
+        this.command(MADCTL, &[panel.orientation.madctl()])?;
+        this.command(INVON, &[])?;
+        this.command(NORON, &[])?;
+        this.command(DISPON, &[])?;
+        delay.delay_ms(10);
+        Ok(this)
+    }
+    /// Releases the SPI peripheral and the data/command pin
+    pub fn free(self) -> (SPI, DC) {
+        (self.spi, self.dc)
+    }
+    /// Returns the panel geometry
+    pub fn panel(&self) -> &Panel {
+        &self.panel
+    }
+    fn command(&mut self, command: u8, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.dc.set_low().unwrap();
+        self.spi.write(&[command]).map_err(Error::Spi)?;
+        self.dc.set_high().unwrap();
+        if !data.is_empty() {
+            self.spi.write(data).map_err(Error::Spi)?;
+        }
+        Ok(())
+    }
+    /// Selects the area that following pixel data is written to
+    fn set_window(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+    ) -> Result<(), Error<SPI::Error>> {
+        if width == 0
+            || height == 0
+            || u32::from(x) + u32::from(width) > u32::from(self.panel.width)
+            || u32::from(y) + u32::from(height) > u32::from(self.panel.height)
+        {
+            return Err(Error::OutOfBounds);
+        }
+        // The offsets move the window into the controller RAM, which has to be addressable
+        let x0 = x.checked_add(self.panel.x_offset);
+        let y0 = y.checked_add(self.panel.y_offset);
+        let x1 = x0.and_then(|x0| x0.checked_add(width - 1));
+        let y1 = y0.and_then(|y0| y0.checked_add(height - 1));
+        let (Some(x0), Some(y0), Some(x1), Some(y1)) = (x0, y0, x1, y1) else {
+            return Err(Error::OutOfBounds);
+        };
+        let [x0h, x0l] = x0.to_be_bytes();
+        let [x1h, x1l] = x1.to_be_bytes();
+        let [y0h, y0l] = y0.to_be_bytes();
+        let [y1h, y1l] = y1.to_be_bytes();
+        self.command(CASET, &[x0h, x0l, x1h, x1l])?;
+        self.command(RASET, &[y0h, y0l, y1h, y1l])?;
+        self.command(RAMWR, &[])
+    }
+    /// Streams pixels into the current window
+    fn write_pixels(
+        &mut self,
+        pixels: impl IntoIterator<Item = u16>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let mut buffer = [0; CHUNK * 2];
+        let mut len = 0;
+        for pixel in pixels {
+            buffer[len..len + 2].copy_from_slice(&pixel.to_be_bytes());
+            len += 2;
+            if len == buffer.len() {
+                self.spi.write(&buffer).map_err(Error::Spi)?;
+                len = 0;
+            }
+        }
+        if len != 0 {
+            self.spi.write(&buffer[..len]).map_err(Error::Spi)?;
+        }
+        Ok(())
+    }
+    /// Fills a rectangle with a RGB565 color
+    ///
+    /// # Errors
+    /// This function returns an error if the rectangle is not inside the panel or the SPI
+    /// transfer fails.
+    pub fn fill_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: u16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.set_window(x, y, width, height)?;
+        let count = u32::from(width) * u32::from(height);
+        self.write_pixels((0..count).map(|_| color))
+    }
+    /// Fills the whole panel with a RGB565 color
+    ///
+    /// # Errors
+    /// This function returns an error if the SPI transfer fails.
+    pub fn clear(&mut self, color: u16) -> Result<(), Error<SPI::Error>> {
+        self.fill_rect(0, 0, self.panel.width, self.panel.height, color)
+    }
+    /// Draws an image with its top left corner at the given position
+    ///
+    /// # Errors
+    /// This function returns an error if the image is not inside the panel or the SPI transfer
+    /// fails.
+    pub fn draw_image(&mut self, x: u16, y: u16, image: &Image) -> Result<(), Error<SPI::Error>> {
+        self.set_window(x, y, image.width(), image.height())?;
+        self.write_pixels(image.pixels())
+    }
+}
+
+/// Run-length encoded RGB565 image
+///
+/// The format is a little endian `u16` width and height, followed by runs of a one byte
+/// repeat count and a little endian `u16` color. Images are meant to be embedded with
+/// `include_bytes!` and are read directly from flash.
+#[derive(Copy, Clone, Debug)]
+pub struct Image {
+    data: &'static [u8],
+}
+
+impl Image {
+    /// Wraps encoded image data
+    ///
+    /// # Panics
+    /// This function panics if the data is too short to contain the header or ends in a
+    /// truncated run.
+    pub const fn new(data: &'static [u8]) -> Self {
+        if data.len() < 4 || (data.len() - 4) % 3 != 0 {
+            panic!("invalid RLE image");
+        }
+        Self { data }
+    }
+    /// Width of the image
+    pub const fn width(&self) -> u16 {
+        u16::from_le_bytes([self.data[0], self.data[1]])
+    }
+    /// Height of the image
+    pub const fn height(&self) -> u16 {
+        u16::from_le_bytes([self.data[2], self.data[3]])
+    }
+    /// Decodes the pixels of the image, row by row
+    ///
+    /// The runs are truncated or padded with black to match the size of the image.
+    pub fn pixels(&self) -> impl Iterator<Item = u16> + 'static {
+        let count = usize::from(self.width()) * usize::from(self.height());
+        let data: &'static [u8] = self.data;
+        data[4..]
+            .chunks_exact(3)
+            .flat_map(|run| {
+                let color = u16::from_le_bytes([run[1], run[2]]);
+                core::iter::repeat(color).take(usize::from(run[0]))
+            })
+            .chain(core::iter::repeat(0))
+            .take(count)
+    }
+}
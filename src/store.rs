@@ -0,0 +1,436 @@
+//! Settings storage
+//!
+//! Settings are kept as a single record in one of two slots of a [`Flash`] region. A save goes
+//! to the slot that does not hold the current record and programs the header last, so losing
+//! power mid-write leaves the previous record intact. On startup the newest record with a
+//! valid checksum wins. Slots that were left half-written are counted as torn writes and
+//! erased, so they are only reported once.
+
+use crate::{
+    crc::{Crc, Crc32},
+    flash::{Flash, FlashError},
+};
+
+/// Marks a programmed slot header ("RKBS")
+const MAGIC: u32 = u32::from_le_bytes(*b"RKBS");
+/// Size of the slot header: magic, sequence number, length and CRC-32
+const HEADER_SIZE: usize = 16;
+/// Size of the staging buffer for programming
+const CHUNK: usize = 256;
+
+/// Error returned by the settings store
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub enum StoreError {
+    /// The flash driver returned an error
+    Flash(FlashError),
+    /// The record does not fit into a slot, or the buffer is too small for the record
+    TooLarge,
+}
+
+impl From<FlashError> for StoreError {
+    fn from(e: FlashError) -> Self {
+        StoreError::Flash(e)
+    }
+}
+
+/// Statistics of the settings store
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct StoreStats {
+    /// Records saved since startup
+    pub saves: u32,
+    /// Half-written slots found on startup
+    pub torn_writes: u32,
+}
+
+/// Header of a valid record
+#[derive(Copy, Clone)]
+struct Record {
+    slot: usize,
+    seq: u32,
+    len: usize,
+}
+
+/// State of a slot found during recovery
+enum Slot {
+    Empty,
+    Valid(Record),
+    Torn,
+}
+
+/// Power-loss safe settings store on top of a flash region
+pub struct Store<F: Flash> {
+    flash: F,
+    current: Option<Record>,
+    stats: StoreStats,
+}
+
+impl<F: Flash> Store<F> {
+    /// Opens the store in `flash`, recovering the newest valid record
+    ///
+    /// # Errors
+    /// This function returns an error if reading the flash fails.
+    ///
+    /// # Panics
+    /// This function panics if the region is too small for two slots or the page size of the
+    /// flash is not supported.
+    pub fn new(flash: F) -> Result<Self, StoreError> {
+        assert!(
+            F::PAGE_SIZE >= HEADER_SIZE && CHUNK % F::PAGE_SIZE == 0,
+            "unsupported flash page size"
+        );
+        let mut store = Self {
+            flash,
+            current: None,
+            stats: StoreStats::default(),
+        };
+        assert!(
+            store.slot_size() > F::PAGE_SIZE,
+            "flash region too small for two slots"
+        );
+        for slot in 0..2 {
+            match store.check(slot)? {
+                Slot::Empty => {}
+                Slot::Torn => {
+                    store.stats.torn_writes += 1;
+                    let base = store.slot_offset(slot);
+                    store.flash.erase(base..base + store.slot_size() as u32)?;
+                }
+                Slot::Valid(record) => {
+                    let newer = match store.current {
+                        Some(current) => record.seq.wrapping_sub(current.seq) as i32 > 0,
+                        None => true,
+                    };
+                    if newer {
+                        store.current = Some(record);
+                    }
+                }
+            }
+        }
+        Ok(store)
+    }
+    /// Returns the largest record that can be stored, in bytes
+    pub fn capacity(&self) -> usize {
+        self.slot_size() - F::PAGE_SIZE
+    }
+    /// Returns the statistics of the store
+    pub const fn stats(&self) -> StoreStats {
+        self.stats
+    }
+    /// Reads the current record into `buf` and returns its length, or `None` if nothing was
+    /// saved yet
+    ///
+    /// # Errors
+    /// This function returns an error if `buf` is too small or reading the flash fails.
+    pub fn load(&mut self, buf: &mut [u8]) -> Result<Option<usize>, StoreError> {
+        let record = match self.current {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let buf = buf.get_mut(..record.len).ok_or(StoreError::TooLarge)?;
+        self.flash.read(self.data_offset(record.slot), buf)?;
+        Ok(Some(record.len))
+    }
+    /// Saves `data` as the new record
+    ///
+    /// # Errors
+    /// This function returns an error if `data` does not fit into a slot or writing the flash
+    /// fails. The previous record stays valid in that case.
+    pub fn save(&mut self, data: &[u8]) -> Result<(), StoreError> {
+        if data.len() > self.capacity() {
+            return Err(StoreError::TooLarge);
+        }
+        let (slot, seq) = match self.current {
+            Some(current) => (1 - current.slot, current.seq.wrapping_add(1)),
+            None => (0, 1),
+        };
+        let base = self.slot_offset(slot);
+        self.flash.erase(base..base + self.slot_size() as u32)?;
+
+        let mut crc = Crc32::new();
+        crc.update(&seq.to_le_bytes());
+        crc.update(&(data.len() as u32).to_le_bytes());
+        crc.update(data);
+
+        let mut buf = [0xFF; CHUNK];
+        let mut offset = self.data_offset(slot);
+        for chunk in data.chunks(CHUNK) {
+            buf[..chunk.len()].copy_from_slice(chunk);
+            buf[chunk.len()..].fill(0xFF);
+            let len = (chunk.len() + F::PAGE_SIZE - 1) / F::PAGE_SIZE * F::PAGE_SIZE;
+            self.flash.write(offset, &buf[..len])?;
+            offset += len as u32;
+        }
+
+        buf.fill(0xFF);
+        buf[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[4..8].copy_from_slice(&seq.to_le_bytes());
+        buf[8..12].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        buf[12..16].copy_from_slice(&crc.finish().to_le_bytes());
+        self.flash.write(base, &buf[..F::PAGE_SIZE])?;
+
+        self.current = Some(Record {
+            slot,
+            seq,
+            len: data.len(),
+        });
+        self.stats.saves += 1;
+        Ok(())
+    }
+    /// Consumes the store, returning the flash region
+    pub fn free(self) -> F {
+        self.flash
+    }
+    /// Size of a slot, rounded down to whole erase sectors
+    fn slot_size(&self) -> usize {
+        self.flash.capacity() / 2 / F::ERASE_SIZE * F::ERASE_SIZE
+    }
+    fn slot_offset(&self, slot: usize) -> u32 {
+        (slot * self.slot_size()) as u32
+    }
+    fn data_offset(&self, slot: usize) -> u32 {
+        self.slot_offset(slot) + F::PAGE_SIZE as u32
+    }
+    /// Returns `true` if the whole slot is erased
+    fn is_erased(&mut self, slot: usize) -> Result<bool, FlashError> {
+        let mut buf = [0; CHUNK];
+        let end = self.slot_offset(slot) + self.slot_size() as u32;
+        let mut offset = self.slot_offset(slot);
+        while offset < end {
+            let n = CHUNK.min((end - offset) as usize);
+            self.flash.read(offset, &mut buf[..n])?;
+            if buf[..n].iter().any(|&b| b != 0xFF) {
+                return Ok(false);
+            }
+            offset += n as u32;
+        }
+        Ok(true)
+    }
+    /// Validates the header and checksum of a slot
+    fn check(&mut self, slot: usize) -> Result<Slot, FlashError> {
+        let mut header = [0; HEADER_SIZE];
+        self.flash.read(self.slot_offset(slot), &mut header)?;
+        let word =
+            |i: usize| u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+        let (magic, seq, len, expected) = (word(0), word(4), word(8), word(12));
+        if header.iter().all(|&b| b == 0xFF) {
+            // A write that was interrupted before the header leaves data or a partially erased
+            // sector behind, anywhere in the slot
+            return Ok(if self.is_erased(slot)? {
+                Slot::Empty
+            } else {
+                Slot::Torn
+            });
+        }
+        let len = len as usize;
+        if magic != MAGIC || len > self.capacity() {
+            return Ok(Slot::Torn);
+        }
+        let mut crc = Crc32::new();
+        crc.update(&seq.to_le_bytes());
+        crc.update(&(len as u32).to_le_bytes());
+        let mut buf = [0; CHUNK];
+        let mut offset = self.data_offset(slot);
+        let mut remaining = len;
+        while remaining > 0 {
+            let n = remaining.min(CHUNK);
+            self.flash.read(offset, &mut buf[..n])?;
+            crc.update(&buf[..n]);
+            offset += n as u32;
+            remaining -= n;
+        }
+        Ok(if crc.finish() == expected {
+            Slot::Valid(Record { slot, seq, len })
+        } else {
+            Slot::Torn
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ops::Range;
+
+    const SECTOR: usize = 4096;
+    const PAGE: usize = 256;
+
+    /// Flash in RAM that can lose power between two page programs or sector erases
+    struct RamFlash {
+        data: Vec<u8>,
+        /// Number of pages or sectors that are completed before power is lost
+        budget: Option<usize>,
+    }
+
+    impl RamFlash {
+        fn new() -> Self {
+            Self {
+                data: vec![0xFF; 4 * SECTOR],
+                budget: None,
+            }
+        }
+        /// Returns `false` if power was lost before the next step
+        fn step(&mut self) -> bool {
+            match &mut self.budget {
+                Some(0) => false,
+                Some(budget) => {
+                    *budget -= 1;
+                    true
+                }
+                None => true,
+            }
+        }
+    }
+
+    impl Flash for RamFlash {
+        const PAGE_SIZE: usize = PAGE;
+        const ERASE_SIZE: usize = SECTOR;
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+        fn read(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), FlashError> {
+            let offset = offset as usize;
+            buf.copy_from_slice(&self.data[offset..offset + buf.len()]);
+            Ok(())
+        }
+        fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), FlashError> {
+            assert!(offset as usize % PAGE == 0 && data.len() % PAGE == 0);
+            for (i, page) in data.chunks(PAGE).enumerate() {
+                if !self.step() {
+                    break;
+                }
+                let start = offset as usize + i * PAGE;
+                // Programming can only clear bits
+                for (byte, new) in self.data[start..start + PAGE].iter_mut().zip(page) {
+                    *byte &= new;
+                }
+            }
+            Ok(())
+        }
+        fn erase(&mut self, range: Range<u32>) -> Result<(), FlashError> {
+            assert!(range.start as usize % SECTOR == 0 && range.end as usize % SECTOR == 0);
+            for start in (range.start as usize..range.end as usize).step_by(SECTOR) {
+                if !self.step() {
+                    break;
+                }
+                self.data[start..start + SECTOR].fill(0xFF);
+            }
+            Ok(())
+        }
+    }
+
+    fn load(store: &mut Store<RamFlash>) -> Option<Vec<u8>> {
+        let mut buf = vec![0; store.capacity()];
+        let len = store.load(&mut buf).unwrap()?;
+        buf.truncate(len);
+        Some(buf)
+    }
+
+    /// Simulates a reboot
+    fn reopen(store: Store<RamFlash>) -> Store<RamFlash> {
+        let mut flash = store.free();
+        flash.budget = None;
+        Store::new(flash).unwrap()
+    }
+
+    #[test]
+    fn empty() {
+        let mut store = Store::new(RamFlash::new()).unwrap();
+        assert_eq!(load(&mut store), None);
+        assert_eq!(store.stats(), StoreStats::default());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut store = Store::new(RamFlash::new()).unwrap();
+        store.save(b"first").unwrap();
+        store.save(b"second").unwrap();
+        assert_eq!(load(&mut store).as_deref(), Some(&b"second"[..]));
+        let mut store = reopen(store);
+        assert_eq!(load(&mut store).as_deref(), Some(&b"second"[..]));
+        assert_eq!(store.stats().torn_writes, 0);
+    }
+
+    #[test]
+    fn full_slot() {
+        let mut store = Store::new(RamFlash::new()).unwrap();
+        let data: Vec<u8> = (0..store.capacity()).map(|i| i as u8).collect();
+        store.save(&data).unwrap();
+        assert_eq!(
+            store.save(&vec![0; store.capacity() + 1]),
+            Err(StoreError::TooLarge)
+        );
+        let mut store = reopen(store);
+        assert_eq!(load(&mut store), Some(data));
+        assert_eq!(store.load(&mut [0; 16]), Err(StoreError::TooLarge));
+    }
+
+    #[test]
+    fn seq_wraparound() {
+        let mut store = Store::new(RamFlash::new()).unwrap();
+        store.current = Some(Record {
+            slot: 1,
+            seq: u32::MAX - 1,
+            len: 0,
+        });
+        store.save(b"max").unwrap();
+        store.save(b"zero").unwrap();
+        let mut store = reopen(store);
+        assert_eq!(load(&mut store).as_deref(), Some(&b"zero"[..]));
+        store.save(b"one").unwrap();
+        let mut store = reopen(store);
+        assert_eq!(load(&mut store).as_deref(), Some(&b"one"[..]));
+    }
+
+    #[test]
+    fn corrupted_header() {
+        let mut store = Store::new(RamFlash::new()).unwrap();
+        store.save(b"old").unwrap();
+        store.save(b"new").unwrap();
+        let mut flash = store.free();
+        flash.data[4 * SECTOR / 2 + 12] = 0;
+        let mut store = Store::new(flash).unwrap();
+        assert_eq!(load(&mut store).as_deref(), Some(&b"old"[..]));
+        assert_eq!(store.stats().torn_writes, 1);
+        let store = reopen(store);
+        assert_eq!(store.stats().torn_writes, 0);
+    }
+
+    #[test]
+    fn power_loss() {
+        // Data that starts like an erased slot
+        let mut new = vec![0xFF; 4];
+        new.extend((0..600).map(|i| i as u8));
+        // Erasing both sectors of the slot, programming the data, then the header
+        let pages = (new.len() + PAGE - 1) / PAGE;
+        let steps = 2 + pages + 1;
+        for step in 0..=steps {
+            let mut store = Store::new(RamFlash::new()).unwrap();
+            store.save(b"old").unwrap();
+            store.save(b"older slot").unwrap();
+            store.save(b"old").unwrap();
+            let mut flash = store.free();
+            flash.budget = Some(step);
+            let mut store = Store::new(flash).unwrap();
+            store.save(&new).unwrap();
+
+            let mut store = reopen(store);
+            let expected = if step == steps { &new[..] } else { &b"old"[..] };
+            assert_eq!(load(&mut store).as_deref(), Some(expected), "step {}", step);
+            // Data without a header is torn, an erased slot is not
+            let torn = u32::from((3..steps).contains(&step));
+            assert_eq!(store.stats().torn_writes, torn, "step {}", step);
+
+            let mut store = reopen(store);
+            assert_eq!(store.stats().torn_writes, 0, "step {}", step);
+            store.save(b"after").unwrap();
+            let mut store = reopen(store);
+            assert_eq!(
+                load(&mut store).as_deref(),
+                Some(&b"after"[..]),
+                "step {}",
+                step
+            );
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Capacitive touch keys
+//!
+//! Uses charge timing: the pad is charged through a high value resistor (about 1 MΩ) from a
+//! send pin, and the time until the receive pin reads high grows with the capacitance of the
+//! pad. A finger on the pad adds capacitance and therefore delay.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use crate::filter::Ema;
+
+/// A pad whose charge time can be measured
+pub trait TouchPad {
+    /// Measures the charge time of the pad in loop iterations, giving up after `timeout`
+    fn measure(&mut self, timeout: u32) -> u32;
+}
+
+/// Pad charged through a resistor between a send pin and the pad's receive pin
+pub struct ChargeTimePad<S, R> {
+    send: S,
+    receive: R,
+}
+
+impl<S, R> ChargeTimePad<S, R>
+where
+    S: OutputPin<Error = Infallible>,
+    R: InputPin<Error = Infallible>,
+{
+    /// Creates a new pad
+    pub fn new(mut send: S, receive: R) -> Self {
+        send.set_low().unwrap();
+        Self { send, receive }
+    }
+    /// Releases the pins
+    pub fn free(self) -> (S, R) {
+        (self.send, self.receive)
+    }
+}
+
+impl<S, R> TouchPad for ChargeTimePad<S, R>
+where
+    S: OutputPin<Error = Infallible>,
+    R: InputPin<Error = Infallible>,
+{
+    fn measure(&mut self, timeout: u32) -> u32 {
+        self.send.set_low().unwrap();
+        let mut discharge = 0;
+        while self.receive.is_high().unwrap() && discharge < timeout {
+            discharge += 1;
+        }
+        self.send.set_high().unwrap();
+        let mut count = 0;
+        while self.receive.is_low().unwrap() && count < timeout {
+            count += 1;
+        }
+        self.send.set_low().unwrap();
+        count
+    }
+}
+
+/// Touch detection parameters
+#[derive(Copy, Clone, Debug, PartialEq, Eq, defmt::Format)]
+pub struct TouchConfig {
+    /// Maximum number of loop iterations per measurement
+    pub timeout: u32,
+    /// Increase over the baseline that counts as a touch
+    pub press_threshold: u32,
+    /// Increase over the baseline below which a touch is released
+    pub release_threshold: u32,
+    /// Shift of the baseline filter, below 32; higher values adapt more slowly
+    pub baseline_shift: u8,
+}
+
+impl Default for TouchConfig {
+    fn default() -> Self {
+        Self {
+            timeout: 10_000,
+            press_threshold: 40,
+            release_threshold: 25,
+            baseline_shift: 6,
+        }
+    }
+}
+
+/// A touch key
+///
+/// The key tracks the untouched charge time of its pad so slow drift from temperature and
+/// humidity does not register as a touch. The key state is raw and has to pass through the same
+/// debouncing as matrix keys.
+pub struct TouchKey<P> {
+    pad: P,
+    config: TouchConfig,
+    /// Baseline charge time
+    baseline: Ema<u32>,
+    pressed: bool,
+}
+
+impl<P: TouchPad> TouchKey<P> {
+    /// Creates a new touch key, calibrating the baseline
+    ///
+    /// The pad must not be touched while calibrating.
+    ///
+    /// # Panics
+    /// This function panics if the baseline shift is 32 or more.
+    pub fn new(mut pad: P, config: TouchConfig) -> Self {
+        let mut baseline = Ema::<u32>::new(config.baseline_shift);
+        let sum: u64 = (0..16)
+            .map(|_| u64::from(pad.measure(config.timeout)))
+            .sum();
+        // The average of 16 `u32` samples fits into a `u32`
+        baseline.update((sum / 16) as u32);
+        Self {
+            pad,
+            config,
+            baseline,
+            pressed: false,
+        }
+    }
+    /// Releases the pad
+    pub fn free(self) -> P {
+        self.pad
+    }
+    /// Returns `true` if the key was touched on the last scan
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+    /// Measures the pad and returns whether it is touched
+    pub fn scan(&mut self) -> bool {
+        let sample = self.pad.measure(self.config.timeout);
+        let delta = sample.saturating_sub(self.baseline.value());
+        self.pressed = if self.pressed {
+            delta > self.config.release_threshold
+        } else {
+            delta > self.config.press_threshold
+        };
+        if !self.pressed {
+            // Only track the baseline while untouched
+            self.baseline.update(sample);
+        }
+        self.pressed
+    }
+}